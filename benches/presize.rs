@@ -0,0 +1,46 @@
+//! Compares loading a large map with and without reserving capacity beforehand.
+//!
+//! Run with `cargo bench --bench presize`.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use koit::format::{Bincode, Format, Json, PreSized, PreSizedHashMap};
+
+const ENTRIES: u64 = 1_000_000;
+const RUNS: u32 = 5;
+
+fn time<T>(mut load: impl FnMut() -> T) -> Duration {
+    let mut best = Duration::MAX;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        std::hint::black_box(load());
+        best = best.min(start.elapsed());
+    }
+    best
+}
+
+fn compare<Plain>(name: &str)
+where
+    Plain: Format<HashMap<u64, u64>>,
+    PreSized<Plain>: Format<PreSizedHashMap<u64, u64>>,
+{
+    let fixture: HashMap<u64, u64> = (0..ENTRIES).map(|n| (n, n.wrapping_mul(31))).collect();
+    let bytes = Plain::to_bytes(&fixture).expect("the fixture encodes");
+
+    let plain = time(|| Plain::from_bytes(bytes.clone()).expect("the fixture decodes"));
+    let sized = time(|| <PreSized<Plain>>::from_bytes(bytes.clone()).expect("the fixture decodes"));
+    println!(
+        "{}: {} entries, {:?} plain, {:?} pre-sized ({:.2}x)",
+        name,
+        ENTRIES,
+        plain,
+        sized,
+        plain.as_secs_f64() / sized.as_secs_f64()
+    );
+}
+
+fn main() {
+    compare::<Json>("json");
+    compare::<Bincode>("bincode");
+}
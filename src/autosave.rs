@@ -0,0 +1,151 @@
+use std::sync::Weak;
+use std::time::Duration;
+
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+use crate::{Backend, Database, Durability, Format, KoitError, Result};
+
+/// How many background save errors are kept until they are observed. Further errors are dropped.
+const ERROR_BUFFER: usize = 16;
+
+/// A handle to a database's background save task, created by
+/// [`Database::with_autosave`](crate::Database::with_autosave).
+///
+/// The task saves the database whenever it is dirty, at most once per interval. If nothing was
+/// written since the last save, no save happens. Failed saves are reported through
+/// [`Autosave::next_error`](crate::Autosave::next_error); the database stays dirty, so the save
+/// is retried on the next interval.
+///
+/// Dropping the handle stops the task after one last save. Use
+/// [`Autosave::shutdown`](crate::Autosave::shutdown) to wait for that save and observe its
+/// result.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// use async_trait::async_trait;
+/// use koit::{Backend, Database, format::Json};
+///
+/// struct Broken;
+///
+/// #[async_trait]
+/// impl Backend for Broken {
+///     type Error = std::io::Error;
+///
+///     async fn read(&mut self) -> Result<Vec<u8>, Self::Error> {
+///         Ok(b"0".to_vec())
+///     }
+///     async fn write(&mut self, _data: Vec<u8>) -> Result<(), Self::Error> {
+///         Err(std::io::Error::other("disk on fire"))
+///     }
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let db: Arc<Database<u32, Broken, Json>> = Arc::new(Database::from_parts(0, Broken));
+///     let mut autosave = db.with_autosave(Duration::from_millis(10));
+///
+///     db.write(|n| *n = 42).await;
+///     assert!(matches!(autosave.next_error().await, Some(koit::KoitError::BackendWrite(_))));
+///     assert!(db.is_dirty());
+///
+///     assert!(autosave.shutdown().await.is_err());
+/// }
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "autosave")))]
+#[derive(Debug)]
+pub struct Autosave {
+    stop: oneshot::Sender<()>,
+    task: JoinHandle<Result<()>>,
+    errors: mpsc::Receiver<KoitError>,
+}
+
+impl Autosave {
+    /// Wait for the next error of a background save.
+    ///
+    /// Returns `None` once the background task has stopped and all errors have been observed.
+    pub async fn next_error(&mut self) -> Option<KoitError> {
+        self.errors.recv().await
+    }
+
+    /// Take an already reported error of a background save, if any.
+    pub fn try_next_error(&mut self) -> Option<KoitError> {
+        self.errors.try_recv().ok()
+    }
+
+    /// Stop the background task, saving one last time if the database is dirty.
+    ///
+    /// # Errors
+    ///
+    /// If the last save fails, its error is returned.
+    pub async fn shutdown(self) -> Result<()> {
+        let _ = self.stop.send(());
+        match self.task.await {
+            Ok(result) => result,
+            Err(err) if err.is_panic() => std::panic::resume_unwind(err.into_panic()),
+            // The runtime is shutting down; there is nothing left to save with.
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+/// Spawn the background save task. Saves use the given durability, or the database's configured
+/// durability if none is given.
+pub(crate) fn spawn<D, B, F>(
+    db: Weak<Database<D, B, F>>,
+    interval: Duration,
+    durability: Option<Durability>,
+) -> Autosave
+where
+    D: Send + Sync + 'static,
+    B: Backend + Send + 'static,
+    F: Format<D> + Send + Sync + 'static,
+{
+    // Checked here, as the task would only panic once it starts.
+    assert!(
+        !interval.is_zero(),
+        "the autosave interval must be non-zero"
+    );
+    let (stop, mut stopped) = oneshot::channel();
+    let (error_sender, errors) = mpsc::channel(ERROR_BUFFER);
+
+    let task = tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        // The first tick completes immediately.
+        ticker.tick().await;
+
+        loop {
+            tokio::select! {
+                // Either a shutdown was requested or the handle was dropped.
+                _ = &mut stopped => break,
+                _ = ticker.tick() => {}
+            }
+
+            let db = match db.upgrade() {
+                Some(db) => db,
+                None => return Ok(()),
+            };
+            if db.is_dirty() {
+                let durability = durability.unwrap_or_else(|| db.durability());
+                if let Err(err) = db.save_with(durability).await {
+                    let _ = error_sender.try_send(err);
+                }
+            }
+        }
+
+        match db.upgrade() {
+            Some(db) if db.is_dirty() => {
+                let durability = durability.unwrap_or_else(|| db.durability());
+                db.save_with(durability).await.map(drop)
+            }
+            _ => Ok(()),
+        }
+    });
+
+    Autosave { stop, task, errors }
+}
@@ -35,6 +35,10 @@
 
 use async_trait::async_trait;
 
+/// The future returned by the provided async methods of [`Backend`]. They are written out
+/// instead of using `async fn`, which would require the backend to be `Send` to call them.
+type Pending<'a, T> = std::pin::Pin<Box<dyn std::future::Future<Output = T> + Send + 'a>>;
+
 /// Trait implementable by bytes storage providers.
 ///
 /// # Examples
@@ -51,37 +55,270 @@ pub trait Backend {
     /// If the bytes failed to be read by the backend, an error variant is returned.
     async fn read(&mut self) -> Result<Vec<u8>, Self::Error>;
 
-    /// Overwrite the backend with the given data.
+    /// Overwrite the backend with the given data. The data need not be durable until the
+    /// backend is [flushed](crate::backend::Backend::flush).
     ///
     /// # Errors
     ///
     /// If the bytes failed to be written to the backend, an error variant is returned.
     /// This may mean the backend is now corrupted.
     async fn write(&mut self, data: Vec<u8>) -> Result<(), Self::Error>;
+
+    /// Make previously written data durable, for example by syncing it to disk. This is the
+    /// strongest level of [`Durability`](crate::Durability). The default implementation does
+    /// nothing.
+    ///
+    /// # Errors
+    ///
+    /// If the data failed to be made durable, an error variant is returned.
+    fn flush<'a, 'async_trait>(&'a mut self) -> Pending<'async_trait, Result<(), Self::Error>>
+    where
+        'a: 'async_trait,
+        Self: 'async_trait,
+    {
+        Box::pin(async { Ok(()) })
+    }
+
+    /// Make previously written data durable to the given level. See
+    /// [`Durability`](crate::Durability) for what each level guarantees per backend; wrapping
+    /// backends pass the level on. The default implementation does nothing for
+    /// [`Durability::Buffered`](crate::Durability::Buffered), and
+    /// [flushes](crate::backend::Backend::flush) otherwise.
+    ///
+    /// # Errors
+    ///
+    /// If the data failed to be made durable, an error variant is returned.
+    fn flush_with<'a, 'async_trait>(
+        &'a mut self,
+        durability: Durability,
+    ) -> Pending<'async_trait, Result<(), Self::Error>>
+    where
+        'a: 'async_trait,
+        Self: 'async_trait,
+    {
+        match durability {
+            Durability::Buffered => Box::pin(async { Ok(()) }),
+            Durability::Flushed | Durability::FlushedPlusDirSync => self.flush(),
+        }
+    }
+
+    /// Announce the durability the next write will be [flushed](crate::backend::Backend::flush_with)
+    /// with, before it is written. Backends that make data durable while writing, such as
+    /// [`FilePath`](crate::backend::FilePath) syncing its temporary file before renaming it over
+    /// the file, can skip that for [`Durability::Buffered`](crate::Durability::Buffered). Saving
+    /// calls this before every write; wrapping backends pass it on. The default implementation
+    /// does nothing.
+    fn prepare_write(&mut self, _durability: Durability) {}
+
+    /// Returns a writer that overwrites the data in the backend, if the backend supports
+    /// streaming. Saving then encodes the data straight into the writer, instead of into an
+    /// intermediate buffer passed to [`Backend::write`](crate::backend::Backend::write). The
+    /// default implementation does not support streaming.
+    ///
+    /// The writer is flushed by the caller once all data has been written, after which the
+    /// caller drops it and calls [`Backend::finish_write`](crate::backend::Backend::finish_write).
+    /// As with [`Backend::write`](crate::backend::Backend::write), the data need not be durable
+    /// until the backend is [flushed](crate::backend::Backend::flush).
+    ///
+    /// Encoding can fail partway, in which case `finish_write` is not called. The stored data
+    /// must therefore stay intact until `finish_write`, for example by writing to a temporary
+    /// file.
+    ///
+    /// # Errors
+    ///
+    /// If the backend failed to prepare for writing, an error variant is returned.
+    ///
+    /// # Examples
+    ///
+    /// A failed encoding leaves the previous save in place:
+    ///
+    /// ```
+    /// use koit::{Database, KoitError, backend::{File, Memory}, format::Json};
+    /// use serde::{Deserialize, Deserializer, Serialize, Serializer, ser::{Error, SerializeSeq}};
+    ///
+    /// /// Numbers that fail to encode once they contain a zero.
+    /// struct Numbers(Vec<u32>);
+    ///
+    /// impl Serialize for Numbers {
+    ///     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    ///         let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
+    ///         for n in &self.0 {
+    ///             if *n == 0 {
+    ///                 return Err(S::Error::custom("zero"));
+    ///             }
+    ///             seq.serialize_element(n)?;
+    ///         }
+    ///         seq.end()
+    ///     }
+    /// }
+    ///
+    /// impl<'de> Deserialize<'de> for Numbers {
+    ///     fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    ///         Vec::deserialize(deserializer).map(Numbers)
+    ///     }
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> koit::Result<()> {
+    ///     let path = std::env::temp_dir().join("koit-failed-encoding-example.json");
+    ///     let _ = std::fs::remove_file(&path);
+    ///     let (file, _exists) = File::from_path_or_create(&path).await.unwrap();
+    ///     let db = Database::<_, _, Json>::from_parts(Numbers(vec![1, 2, 3]), file);
+    ///     db.save().await?;
+    ///
+    ///     db.write(|numbers| numbers.0.push(0)).await;
+    ///     assert!(matches!(db.save().await, Err(KoitError::ToFormat(_))));
+    ///     assert_eq!(std::fs::read(&path).unwrap(), b"[\n  1,\n  2,\n  3\n]");
+    ///     assert!(db.is_dirty());
+    ///
+    ///     let memory = Memory::from(b"[1]".to_vec());
+    ///     let db = Database::<_, _, Json>::from_parts(Numbers(vec![1, 0]), memory);
+    ///     assert!(matches!(db.save().await, Err(KoitError::ToFormat(_))));
+    ///     let (_data, mut memory) = db.into_parts();
+    ///     assert_eq!(memory.take(), b"[1]");
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[allow(clippy::type_complexity)]
+    fn writer(&mut self) -> Result<Option<Box<dyn std::io::Write + Send + '_>>, Self::Error> {
+        Ok(None)
+    }
+
+    /// Complete a write through the [writer](crate::backend::Backend::writer), for example by
+    /// moving a temporary file into place. Not called if writing failed. The default
+    /// implementation does nothing.
+    ///
+    /// # Errors
+    ///
+    /// If the backend failed to complete the write, an error variant is returned.
+    fn finish_write<'a, 'async_trait>(
+        &'a mut self,
+    ) -> Pending<'async_trait, Result<(), Self::Error>>
+    where
+        'a: 'async_trait,
+        Self: 'async_trait,
+    {
+        Box::pin(async { Ok(()) })
+    }
+
+    /// Returns a reader over all data in the backend, if the backend supports streaming. See
+    /// [`Backend::writer`](crate::backend::Backend::writer). The default implementation does not
+    /// support streaming.
+    ///
+    /// # Errors
+    ///
+    /// If the backend failed to prepare for reading, an error variant is returned.
+    #[allow(clippy::type_complexity)]
+    fn reader(&mut self) -> Result<Option<Box<dyn std::io::Read + Send + '_>>, Self::Error> {
+        Ok(None)
+    }
+
+    /// A cheap fingerprint of the stored data, used to detect external changes, see
+    /// [`Database::reload_if_changed`](crate::Database::reload_if_changed). Returns `None` if the
+    /// backend cannot fingerprint its data, which is the default.
+    ///
+    /// # Errors
+    ///
+    /// If the fingerprint failed to be determined by the backend, an error variant is returned.
+    fn fingerprint<'a, 'async_trait>(
+        &'a mut self,
+    ) -> Pending<'async_trait, Result<Option<Fingerprint>, Self::Error>>
+    where
+        'a: 'async_trait,
+        Self: 'async_trait,
+    {
+        Box::pin(async { Ok(None) })
+    }
+
+    /// The path of the file backing this backend, if any. External tools can be pointed at this
+    /// path, for example while the database is [quiesced](crate::Database::quiesce).
+    fn path(&self) -> Option<&std::path::Path> {
+        None
+    }
+}
+
+/// How durable a save is, see [`Database::save_with`](crate::Database::save_with).
+///
+/// Stronger levels survive more failures, at the cost of slower saves. Per backend, the levels
+/// guarantee the following once a save returns:
+///
+/// | Backend | `Buffered` | `Flushed` | `FlushedPlusDirSync` |
+/// |---------|------------|-----------|----------------------|
+/// | [`Memory`](crate::backend::Memory) | The data is stored. | Same. | Same. |
+/// | [`File`](crate::backend::File) | The data is handed to the operating system, and survives the process crashing, but not the system. | The file is synced to disk. | If the backend created the file, the directory is synced as well on the first save, so the new file survives too. |
+/// | [`FilePath`](crate::backend::FilePath), [`BlockingFile`](crate::backend::BlockingFile) | The temporary file replaces the file without being synced, so the new data survives the process crashing, but after a system crash the file may be empty or incomplete. | The temporary file is synced before it replaces the file, so after a system crash either the old or the new data is in place. | The directory is synced as well, so the new data is in place. |
+/// | [`ReadOnly`](crate::backend::ReadOnly) | Passed on to the wrapped backend. | Same. | Same. |
+///
+/// Other backends decide through [`Backend::flush_with`](crate::backend::Backend::flush_with).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Durability {
+    /// Write the data, without syncing it.
+    Buffered,
+    /// Write the data and sync the file.
+    Flushed,
+    /// Write the data, and sync the file and its directory.
+    FlushedPlusDirSync,
+}
+
+/// A fingerprint of the data stored by a backend. Fingerprints differ if the data changed.
+///
+/// See [`Backend::fingerprint`](crate::backend::Backend::fingerprint).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Fingerprint(u64);
+
+impl Fingerprint {
+    /// Create a fingerprint by hashing the given value, such as the bytes of the data, or file
+    /// metadata.
+    pub fn of<T: std::hash::Hash + ?Sized>(value: &T) -> Self {
+        use std::hash::Hasher;
+
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        value.hash(&mut hasher);
+        Self(hasher.finish())
+    }
 }
 
 /// An in-memory backend.
 ///
+/// The backend supports [streaming](crate::backend::Backend::writer): a streamed save encodes
+/// into a fresh buffer, which replaces the data once the encoding succeeded.
+///
 /// # Examples
 ///
 /// See the [backend module documentation](crate::backend).
-#[derive(std::default::Default, Debug, Clone, PartialEq, Eq)]
-pub struct Memory(Vec<u8>);
+#[derive(std::default::Default, Debug, Clone)]
+pub struct Memory {
+    data: Vec<u8>,
+    /// The buffer of a streamed write, until the write is finished.
+    spool: Option<Vec<u8>>,
+}
+
+impl PartialEq for Memory {
+    fn eq(&self, other: &Self) -> bool {
+        self.data == other.data
+    }
+}
+
+impl Eq for Memory {}
 
 impl Memory {
     pub fn new() -> Self {
-        Self(Vec::new())
+        Self::default()
     }
 
     /// Take the data out of the backend, leaving an empty backend in its place.
     pub fn take(&mut self) -> Vec<u8> {
-        std::mem::replace(&mut self.0, Vec::new())
+        std::mem::take(&mut self.data)
     }
 }
 
 impl From<Vec<u8>> for Memory {
     fn from(buf: Vec<u8>) -> Self {
-        Self(buf)
+        Self {
+            data: buf,
+            spool: None,
+        }
     }
 }
 
@@ -90,29 +327,305 @@ impl Backend for Memory {
     type Error = std::convert::Infallible;
 
     async fn read(&mut self) -> Result<Vec<u8>, Self::Error> {
-        Ok(self.0.clone())
+        Ok(self.data.clone())
     }
     async fn write(&mut self, data: Vec<u8>) -> Result<(), Self::Error> {
-        Ok(self.0 = data)
+        self.data = data;
+        Ok(())
+    }
+    async fn fingerprint(&mut self) -> Result<Option<Fingerprint>, Self::Error> {
+        Ok(Some(Fingerprint::of(&self.data)))
+    }
+    /// Returns a writer to a fresh buffer, so a failed encoding leaves the data intact.
+    fn writer(&mut self) -> Result<Option<Box<dyn std::io::Write + Send + '_>>, Self::Error> {
+        Ok(Some(Box::new(self.spool.insert(Vec::new()))))
+    }
+    /// Replaces the data with the buffer written to.
+    async fn finish_write(&mut self) -> Result<(), Self::Error> {
+        if let Some(spool) = self.spool.take() {
+            self.data = spool;
+        }
+        Ok(())
+    }
+    fn reader(&mut self) -> Result<Option<Box<dyn std::io::Read + Send + '_>>, Self::Error> {
+        Ok(Some(Box::new(&self.data[..])))
+    }
+}
+
+/// A backend wrapper refusing all writes. Reads are delegated to the wrapped backend, while
+/// writes fail with [`ReadOnlyError::ReadOnly`](crate::backend::ReadOnlyError::ReadOnly) without
+/// touching it, so an accidental [save](crate::Database::save) fails loudly.
+///
+/// # Examples
+///
+/// ```
+/// use koit::{Database, KoitError, format::Json, backend::{Memory, ReadOnly, ReadOnlyError}};
+///
+/// #[tokio::main]
+/// async fn main() -> koit::Result<()> {
+///     let backend = ReadOnly::new(Memory::from(b"[1, 2]".to_vec()));
+///     let db = Database::<Vec<u32>, _, Json>::load(backend).await?;
+///     assert_eq!(db.read(|data| data.clone()).await, vec![1, 2]);
+///
+///     db.write(|data| data.push(3)).await;
+///     match db.save().await {
+///         Err(KoitError::BackendWrite(err)) => assert!(matches!(
+///             err.downcast_ref::<ReadOnlyError<std::convert::Infallible>>(),
+///             Some(ReadOnlyError::ReadOnly)
+///         )),
+///         _ => panic!("a read-only backend must refuse to save"),
+///     }
+///
+///     let (_data, backend) = db.into_parts();
+///     assert_eq!(backend.into_inner().take(), b"[1, 2]");
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReadOnly<B>(B);
+
+impl<B> ReadOnly<B> {
+    pub fn new(backend: B) -> Self {
+        Self(backend)
+    }
+
+    /// Get a reference to the wrapped backend.
+    pub fn get_ref(&self) -> &B {
+        &self.0
+    }
+
+    /// Take the wrapped backend out of the wrapper.
+    pub fn into_inner(self) -> B {
+        self.0
+    }
+}
+
+/// The error of a [`ReadOnly`](crate::backend::ReadOnly) backend.
+#[derive(Debug, thiserror::Error)]
+pub enum ReadOnlyError<E> {
+    /// The wrapped backend failed.
+    #[error(transparent)]
+    Backend(E),
+    /// A write was refused.
+    #[error("the backend is read-only")]
+    ReadOnly,
+}
+
+#[async_trait]
+impl<B> Backend for ReadOnly<B>
+where
+    B: Backend + Send,
+{
+    type Error = ReadOnlyError<B::Error>;
+
+    async fn read(&mut self) -> Result<Vec<u8>, Self::Error> {
+        self.0.read().await.map_err(ReadOnlyError::Backend)
+    }
+    async fn write(&mut self, _data: Vec<u8>) -> Result<(), Self::Error> {
+        Err(ReadOnlyError::ReadOnly)
+    }
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        self.0.flush().await.map_err(ReadOnlyError::Backend)
+    }
+    async fn flush_with(&mut self, durability: Durability) -> Result<(), Self::Error> {
+        self.0
+            .flush_with(durability)
+            .await
+            .map_err(ReadOnlyError::Backend)
     }
+    fn prepare_write(&mut self, durability: Durability) {
+        self.0.prepare_write(durability)
+    }
+    fn writer(&mut self) -> Result<Option<Box<dyn std::io::Write + Send + '_>>, Self::Error> {
+        Err(ReadOnlyError::ReadOnly)
+    }
+    fn reader(&mut self) -> Result<Option<Box<dyn std::io::Read + Send + '_>>, Self::Error> {
+        self.0.reader().map_err(ReadOnlyError::Backend)
+    }
+    async fn fingerprint(&mut self) -> Result<Option<Fingerprint>, Self::Error> {
+        self.0.fingerprint().await.map_err(ReadOnlyError::Backend)
+    }
+    fn path(&self) -> Option<&std::path::Path> {
+        self.0.path()
+    }
+}
+
+/// The directory containing the file at the given path.
+#[cfg(any(feature = "file-backend", feature = "blocking-file-backend"))]
+fn directory(path: &std::path::Path) -> &std::path::Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => std::path::Path::new("."),
+    }
+}
+
+/// Sync the directory containing the file at the given path, making its entry durable. Only
+/// supported on Unix; elsewhere this does nothing.
+#[cfg(feature = "file-backend")]
+async fn sync_directory(path: &std::path::Path) -> Result<(), std::io::Error> {
+    #[cfg(unix)]
+    tokio::fs::File::open(directory(path))
+        .await?
+        .sync_all()
+        .await?;
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+/// Whether blocking I/O can run without stalling the runtime, which is only the case on the
+/// multi-threaded Tokio runtime, with the `file-streaming` feature. The file backends only stream
+/// if so.
+#[cfg(feature = "file-backend")]
+fn can_block() -> bool {
+    cfg!(feature = "file-streaming")
+        && matches!(
+            tokio::runtime::Handle::try_current(),
+            Ok(handle) if handle.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread
+        )
+}
+
+/// Run a streamed save or load, which may block. If blocking I/O [can run](can_block), the task
+/// runs in [`block_in_place`](tokio::task::block_in_place), which first hands the other tasks of
+/// the worker thread to other workers.
+pub(crate) fn blocking<R>(task: impl FnOnce() -> R) -> R {
+    #[cfg(feature = "file-streaming")]
+    if can_block() {
+        return tokio::task::block_in_place(task);
+    }
+    task()
 }
 
+/// The buffer size used when streaming data to and from files.
+#[cfg(any(feature = "file-backend", feature = "blocking-file-backend"))]
+const BUFFER_SIZE: usize = 64 * 1024;
+
+#[cfg(feature = "blocking-file-backend")]
+pub use self::blocking_file::BlockingFile;
 #[cfg(feature = "file-backend")]
-pub use self::file::File;
+pub use self::file::{File, LockMode};
+#[cfg(feature = "file-backend")]
+pub use self::file_path::FilePath;
 
 #[cfg(feature = "file-backend")]
 mod file {
     use async_trait::async_trait;
     use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
 
-    use super::Backend;
+    use super::{can_block, sync_directory, Backend, Durability, Fingerprint, BUFFER_SIZE};
 
     /// A file-backed backend.
     ///
+    /// With the `file-streaming` feature, the backend supports
+    /// [streaming](crate::backend::Backend::writer) through a fixed-size buffer, so saving and
+    /// loading do not hold the entire encoded data in memory. A streamed save goes to a temporary
+    /// file next to the file, which replaces the file once the encoding succeeded. The file's
+    /// permissions carry over, but hard links to it keep the old data. Backends holding a
+    /// [lock](crate::backend::File::from_path_locked) write in place instead, as replacing the
+    /// file would release the lock.
+    ///
+    /// Streamed I/O blocks, so the backend only streams on the multi-threaded Tokio runtime,
+    /// where each save or load runs in [`block_in_place`](tokio::task::block_in_place) without
+    /// stalling other tasks. On the current-thread runtime, or without the feature, saving and
+    /// loading are buffered instead.
+    ///
     /// Note: this requires its futures to be executed on the Tokio 0.3 runtime.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use koit::{FileDatabase, KoitError, format::{Format, Json}};
+    ///
+    /// /// JSON that can only be streamed.
+    /// struct StreamedJson;
+    ///
+    /// impl Format<Vec<u64>> for StreamedJson {
+    ///     type Error = serde_json::Error;
+    ///
+    ///     fn to_bytes(_value: &Vec<u64>) -> Result<Vec<u8>, Self::Error> {
+    ///         unreachable!("the file backend streams")
+    ///     }
+    ///     fn from_bytes(_data: Vec<u8>) -> Result<Vec<u64>, Self::Error> {
+    ///         unreachable!("the file backend streams")
+    ///     }
+    ///     fn to_writer(value: &Vec<u64>, writer: &mut dyn std::io::Write) -> Result<(), KoitError> {
+    ///         Json::to_writer(value, writer)
+    ///     }
+    ///     fn from_reader(reader: &mut dyn std::io::Read) -> Result<Vec<u64>, KoitError> {
+    ///         Json::from_reader(reader)
+    ///     }
+    /// }
+    ///
+    /// # #[cfg(not(feature = "file-streaming"))]
+    /// # fn main() {}
+    /// # #[cfg(feature = "file-streaming")]
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), KoitError> {
+    ///     let path = std::env::temp_dir().join("koit-streaming-example.json");
+    ///     let _ = std::fs::remove_file(&path);
+    ///
+    ///     let data: Vec<u64> = (0..500_000).collect();
+    ///     let db = FileDatabase::<Vec<u64>, StreamedJson>::load_from_path_or_else(&path, || data.clone())
+    ///         .await?;
+    ///     drop(db);
+    ///     assert!(std::fs::metadata(&path).unwrap().len() > 4 * 1024 * 1024);
+    ///
+    ///     let db = FileDatabase::<Vec<u64>, StreamedJson>::load_from_path(&path).await?;
+    ///     assert_eq!(db.read(|loaded| loaded == &data).await, true);
+    ///     db.save().await?;
+    ///     assert!(!std::env::temp_dir().join("koit-streaming-example.json.tmp").exists());
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// On the current-thread runtime, the same database buffers:
+    ///
+    /// ```
+    /// use koit::{FileDatabase, format::Json};
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> koit::Result<()> {
+    ///     let path = std::env::temp_dir().join("koit-current-thread-example.json");
+    ///     let _ = std::fs::remove_file(&path);
+    ///
+    ///     let db = FileDatabase::<Vec<u64>, Json>::load_from_path_or_default(&path).await?;
+    ///     db.write(|data| data.extend(0..1000)).await;
+    ///     db.save().await?;
+    ///     drop(db);
+    ///
+    ///     let db = FileDatabase::<Vec<u64>, Json>::load_from_path(&path).await?;
+    ///     assert_eq!(db.read(|data| data.len()).await, 1000);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
     #[cfg_attr(docsrs, doc(cfg(feature = "file-backend")))]
     #[derive(Debug)]
-    pub struct File(tokio::fs::File);
+    pub struct File {
+        file: tokio::fs::File,
+        /// A handle to the same open file, for streaming.
+        sync: std::fs::File,
+        /// The temporary file of a streamed write, until the write is finished.
+        spool: Option<std::fs::File>,
+        path: std::path::PathBuf,
+        /// Whether the file is locked, in which case it is not replaced by streamed writes.
+        locked: bool,
+        /// Whether the directory entry of the file is durable, which is only in doubt if the
+        /// backend created the file.
+        entry_synced: bool,
+    }
+
+    /// How a [`File`](crate::backend::File) backend acquires its advisory lock.
+    #[cfg_attr(docsrs, doc(cfg(feature = "file-backend")))]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum LockMode {
+        /// Fail immediately if the lock is held elsewhere.
+        NonBlocking,
+        /// Wait until the lock is released elsewhere.
+        Blocking,
+    }
 
     impl File {
         /// Creates the backend by opening the file at the given path.
@@ -125,13 +638,7 @@ mod file {
         where
             P: AsRef<std::path::Path>,
         {
-            Ok(Self(
-                tokio::fs::OpenOptions::new()
-                    .read(true)
-                    .write(true)
-                    .open(path)
-                    .await?,
-            ))
+            Self::open(path, tokio::fs::OpenOptions::new().read(true).write(true)).await
         }
 
         /// Creates the backend by opening a file at the given path. Creates the file if it
@@ -149,21 +656,141 @@ mod file {
             match backend {
                 Ok(self_) => Ok((self_, true)),
                 Err(err) => match err.kind() {
-                    std::io::ErrorKind::NotFound => Ok((
-                        Self(
+                    std::io::ErrorKind::NotFound => {
+                        let backend = Self::open(
+                            path,
                             tokio::fs::OpenOptions::new()
                                 .read(true)
                                 .write(true)
                                 .create(true)
-                                .open(&path)
-                                .await?,
-                        ),
-                        false,
-                    )),
+                                .truncate(false),
+                        )
+                        .await?;
+                        Ok((
+                            Self {
+                                entry_synced: false,
+                                ..backend
+                            },
+                            false,
+                        ))
+                    }
                     _ => Err(err),
                 },
             }
         }
+
+        /// Same as [`File::from_path`](crate::backend::File::from_path), except an exclusive
+        /// advisory lock is taken on the file (`flock` on Unix, `LockFileEx` on Windows). The lock
+        /// is held for as long as the backend lives, and is released when it is dropped.
+        ///
+        /// The lock is advisory: it only protects against other handles that also lock the file,
+        /// such as a second process opening the database through this method.
+        ///
+        /// # Errors
+        ///
+        /// If the file does not exist or could not be opened for reading and writing, an error
+        /// variant is returned. If the lock is held elsewhere and `mode` is
+        /// [`LockMode::NonBlocking`](crate::backend::LockMode::NonBlocking), an error of kind
+        /// [`WouldBlock`](std::io::ErrorKind::WouldBlock) is returned.
+        ///
+        /// # Examples
+        ///
+        /// ```
+        /// use koit::backend::{File, LockMode};
+        ///
+        /// #[tokio::main]
+        /// async fn main() -> Result<(), std::io::Error> {
+        ///     let path = std::env::temp_dir().join("koit-lock-example.json");
+        ///     let (first, _exists) = File::from_path_or_create_locked(&path, LockMode::NonBlocking).await?;
+        ///
+        ///     let err = File::from_path_locked(&path, LockMode::NonBlocking).await.unwrap_err();
+        ///     assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+        ///
+        ///     drop(first);
+        ///     let _second = File::from_path_locked(&path, LockMode::NonBlocking).await?;
+        ///
+        ///     Ok(())
+        /// }
+        /// ```
+        pub async fn from_path_locked<P>(path: P, mode: LockMode) -> Result<Self, std::io::Error>
+        where
+            P: AsRef<std::path::Path>,
+        {
+            Self::from_path(path).await?.lock(mode).await
+        }
+
+        /// Same as [`File::from_path_or_create`](crate::backend::File::from_path_or_create),
+        /// except an exclusive advisory lock is taken on the file. See
+        /// [`File::from_path_locked`](crate::backend::File::from_path_locked).
+        ///
+        /// # Errors
+        ///
+        /// If the file does not exist, but could not be created, or could not be opened for
+        /// reading and writing, an error variant is returned. If the lock is held elsewhere and
+        /// `mode` is [`LockMode::NonBlocking`](crate::backend::LockMode::NonBlocking), an error of
+        /// kind [`WouldBlock`](std::io::ErrorKind::WouldBlock) is returned.
+        pub async fn from_path_or_create_locked<P>(
+            path: P,
+            mode: LockMode,
+        ) -> Result<(Self, bool), std::io::Error>
+        where
+            P: AsRef<std::path::Path>,
+        {
+            let (backend, exists) = Self::from_path_or_create(path).await?;
+            Ok((backend.lock(mode).await?, exists))
+        }
+
+        async fn open<P>(path: P, options: &tokio::fs::OpenOptions) -> Result<Self, std::io::Error>
+        where
+            P: AsRef<std::path::Path>,
+        {
+            let file = options.open(&path).await?;
+            let sync = file.try_clone().await?.into_std().await;
+            Ok(Self {
+                file,
+                sync,
+                spool: None,
+                path: path.as_ref().to_owned(),
+                locked: false,
+                entry_synced: true,
+            })
+        }
+
+        fn spool_path(&self) -> std::path::PathBuf {
+            let mut path = self.path.clone().into_os_string();
+            path.push(".tmp");
+            path.into()
+        }
+
+        async fn lock(self, mode: LockMode) -> Result<Self, std::io::Error> {
+            // Both handles share the open file, and so the lock.
+            let sync = lock(self.sync, mode).await?;
+            Ok(Self {
+                sync,
+                locked: true,
+                ..self
+            })
+        }
+    }
+
+    /// Take an exclusive advisory lock on `file`, returning it once locked.
+    pub(super) async fn lock(
+        file: std::fs::File,
+        mode: LockMode,
+    ) -> Result<std::fs::File, std::io::Error> {
+        match mode {
+            LockMode::NonBlocking => match file.try_lock() {
+                Ok(()) => Ok(file),
+                Err(std::fs::TryLockError::WouldBlock) => Err(std::io::Error::new(
+                    std::io::ErrorKind::WouldBlock,
+                    "the database file is locked by another handle",
+                )),
+                Err(std::fs::TryLockError::Error(err)) => Err(err),
+            },
+            LockMode::Blocking => tokio::task::spawn_blocking(move || file.lock().map(|()| file))
+                .await
+                .map_err(std::io::Error::other)?,
+        }
     }
 
     #[async_trait]
@@ -172,17 +799,725 @@ mod file {
 
         async fn read(&mut self) -> Result<Vec<u8>, Self::Error> {
             let mut buffer = Vec::new();
-            self.0.seek(std::io::SeekFrom::Start(0)).await?;
-            self.0.read_to_end(&mut buffer).await?;
+            self.file.seek(std::io::SeekFrom::Start(0)).await?;
+            self.file.read_to_end(&mut buffer).await?;
             Ok(buffer)
         }
 
         async fn write(&mut self, data: Vec<u8>) -> Result<(), Self::Error> {
-            self.0.seek(std::io::SeekFrom::Start(0)).await?;
-            self.0.set_len(0).await?;
-            self.0.write_all(&data).await?;
-            self.0.sync_all().await?;
+            self.file.seek(std::io::SeekFrom::Start(0)).await?;
+            self.file.set_len(0).await?;
+            self.file.write_all(&data).await?;
+            self.file.flush().await?;
+            Ok(())
+        }
+
+        /// Syncs the file, and its directory if the backend created the file and has not synced
+        /// the directory yet.
+        async fn flush(&mut self) -> Result<(), Self::Error> {
+            self.file.sync_all().await?;
+            if !self.entry_synced {
+                sync_directory(&self.path).await?;
+                self.entry_synced = true;
+            }
+            Ok(())
+        }
+
+        async fn flush_with(&mut self, durability: Durability) -> Result<(), Self::Error> {
+            match durability {
+                Durability::Buffered => Ok(()),
+                Durability::Flushed => self.file.sync_all().await,
+                Durability::FlushedPlusDirSync => self.flush().await,
+            }
+        }
+
+        /// Returns a writer to a temporary file next to the file, so a failed encoding leaves
+        /// the file intact. Does not stream if the file is locked, or if the temporary file
+        /// cannot be created, e.g. because the directory is not writable.
+        fn writer(&mut self) -> Result<Option<Box<dyn std::io::Write + Send + '_>>, Self::Error> {
+            if self.locked || !can_block() {
+                return Ok(None);
+            }
+            let spool = match std::fs::OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(self.spool_path())
+            {
+                Ok(spool) => spool,
+                Err(_) => return Ok(None),
+            };
+            let spool = &*self.spool.insert(spool);
+            Ok(Some(Box::new(std::io::BufWriter::with_capacity(
+                BUFFER_SIZE,
+                spool,
+            ))))
+        }
+
+        /// Renames the temporary file over the file, carrying over its permissions, and reopens
+        /// the file.
+        async fn finish_write(&mut self) -> Result<(), Self::Error> {
+            let spool = match self.spool.take() {
+                Some(spool) => spool,
+                None => return Ok(()),
+            };
+            spool.set_permissions(self.sync.metadata()?.permissions())?;
+            drop(spool);
+            tokio::fs::rename(self.spool_path(), &self.path).await?;
+            // The directory entry changed.
+            self.entry_synced = false;
+
+            let file = tokio::fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(&self.path)
+                .await?;
+            self.sync = file.try_clone().await?.into_std().await;
+            self.file = file;
+            Ok(())
+        }
+
+        fn reader(&mut self) -> Result<Option<Box<dyn std::io::Read + Send + '_>>, Self::Error> {
+            use std::io::Seek;
+
+            if !can_block() {
+                return Ok(None);
+            }
+            self.sync.seek(std::io::SeekFrom::Start(0))?;
+            Ok(Some(Box::new(std::io::BufReader::with_capacity(
+                BUFFER_SIZE,
+                &self.sync,
+            ))))
+        }
+
+        /// Fingerprints the size and modification time of the file. Changes within the
+        /// modification time resolution of the file system that keep the size are not detected.
+        async fn fingerprint(&mut self) -> Result<Option<Fingerprint>, Self::Error> {
+            let metadata = self.file.metadata().await?;
+            Ok(Some(Fingerprint::of(&(
+                metadata.len(),
+                metadata.modified().ok(),
+            ))))
+        }
+
+        fn path(&self) -> Option<&std::path::Path> {
+            Some(&self.path)
+        }
+    }
+}
+
+#[cfg(feature = "file-backend")]
+mod file_path {
+    use async_trait::async_trait;
+    use tokio::io::AsyncWriteExt;
+
+    use super::{
+        can_block, sync_directory, Backend, Durability, Fingerprint, LockMode, BUFFER_SIZE,
+    };
+
+    /// A backend writing to a file by path. Each write goes to a temporary file next to it, which
+    /// is synced to disk and then renamed over the file. A crash during a save thus leaves either
+    /// the old or the new data in place, never a mix.
+    ///
+    /// Optionally, the backend keeps backups of previous saves. Before the temporary file
+    /// replaces `db.json`, the previous file is shifted to `db.json.1`, that one to `db.json.2`,
+    /// and so on, up to the configured number of backups. The slot just past the last backup is
+    /// removed, in case more backups were kept before; other files next to `db.json` are left
+    /// alone, even if they are named like backups. This lets
+    /// [`FilePathDatabase::load_from_path_or_recover`](crate::FilePathDatabase::load_from_path_or_recover)
+    /// fall back to a backup if the file failed to decode.
+    ///
+    /// With the `file-streaming` feature, the backend supports
+    /// [streaming](crate::backend::Backend::writer) to the temporary file and from the file, on
+    /// the multi-threaded Tokio runtime only, as with [`File`](crate::backend::File).
+    ///
+    /// Note: this requires its futures to be executed on the Tokio runtime.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use koit::{Database, backend::FilePath, format::Json};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> koit::Result<()> {
+    ///     let dir = std::env::temp_dir().join("koit-file-path-example");
+    ///     let _ = std::fs::remove_dir_all(&dir);
+    ///     std::fs::create_dir_all(&dir).unwrap();
+    ///     let path = dir.join("db.json");
+    ///     // A stale backup, from when more backups were kept.
+    ///     std::fs::write(dir.join("db.json.3"), b"0").unwrap();
+    ///     // A file of the user's own.
+    ///     std::fs::write(dir.join("db.json.20261014"), b"0").unwrap();
+    ///
+    ///     let db = Database::<u32, FilePath, Json>::from_parts(0, FilePath::with_backups(&path, 2));
+    ///     for n in 1..=4 {
+    ///         db.write(|data| *data = n).await;
+    ///         db.save().await?;
+    ///     }
+    ///
+    ///     let read = |path: std::path::PathBuf| std::fs::read_to_string(path).unwrap();
+    ///     assert_eq!(read(path.clone()), "4");
+    ///     assert_eq!(read(dir.join("db.json.1")), "3");
+    ///     assert_eq!(read(dir.join("db.json.2")), "2");
+    ///     assert!(!dir.join("db.json.3").exists());
+    ///     assert!(!dir.join("db.json.tmp").exists());
+    ///
+    ///     let db = Database::<u32, FilePath, Json>::from_parts(5, FilePath::new(&path));
+    ///     db.save().await?;
+    ///     assert_eq!(read(dir.join("db.json.20261014")), "0");
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// Saving streams into the temporary file, so the encoding is not buffered as a whole:
+    ///
+    /// ```
+    /// use koit::{Database, KoitError, backend::FilePath, format::{Bincode, Format}};
+    ///
+    /// /// Bincode that can only be streamed.
+    /// struct StreamedBincode;
+    ///
+    /// impl Format<Vec<u64>> for StreamedBincode {
+    ///     type Error = bincode::Error;
+    ///
+    ///     fn to_bytes(_value: &Vec<u64>) -> Result<Vec<u8>, Self::Error> {
+    ///         unreachable!("the backend streams")
+    ///     }
+    ///     fn from_bytes(_data: Vec<u8>) -> Result<Vec<u64>, Self::Error> {
+    ///         unreachable!("the backend streams")
+    ///     }
+    ///     fn to_writer(value: &Vec<u64>, writer: &mut dyn std::io::Write) -> Result<(), KoitError> {
+    ///         Bincode::to_writer(value, writer)
+    ///     }
+    ///     fn from_reader(reader: &mut dyn std::io::Read) -> Result<Vec<u64>, KoitError> {
+    ///         Bincode::from_reader(reader)
+    ///     }
+    /// }
+    ///
+    /// # #[cfg(not(feature = "file-streaming"))]
+    /// # fn main() {}
+    /// # #[cfg(feature = "file-streaming")]
+    /// #[tokio::main]
+    /// async fn main() -> koit::Result<()> {
+    ///     let dir = std::env::temp_dir().join("koit-file-path-streaming-example");
+    ///     let _ = std::fs::remove_dir_all(&dir);
+    ///     std::fs::create_dir_all(&dir).unwrap();
+    ///     let path = dir.join("db.bin");
+    ///
+    ///     let data: Vec<u64> = (0..1_000_000).collect();
+    ///     let db = Database::<_, _, StreamedBincode>::from_parts(data.clone(), FilePath::with_backups(&path, 1));
+    ///     db.save().await?;
+    ///     db.save().await?;
+    ///     assert_eq!(std::fs::metadata(&path).unwrap().len(), 8 + 8 * 1_000_000);
+    ///     assert!(dir.join("db.bin.1").exists());
+    ///     assert!(!dir.join("db.bin.tmp").exists());
+    ///
+    ///     let db = Database::<Vec<u64>, _, StreamedBincode>::load(FilePath::new(&path)).await?;
+    ///     assert_eq!(db.read(|loaded| loaded == &data).await, true);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "file-backend")))]
+    #[derive(Debug, Clone)]
+    pub struct FilePath {
+        path: std::path::PathBuf,
+        backups: usize,
+        /// The locked sentinel file, shared by clones.
+        _lock: Option<std::sync::Arc<std::fs::File>>,
+        /// Whether the next write syncs the temporary file, see
+        /// [`Backend::prepare_write`](crate::backend::Backend::prepare_write).
+        sync_write: bool,
+        /// Whether the file holds a write that was not synced.
+        unsynced: bool,
+    }
+
+    impl PartialEq for FilePath {
+        fn eq(&self, other: &Self) -> bool {
+            self.path == other.path && self.backups == other.backups
+        }
+    }
+
+    impl Eq for FilePath {}
+
+    impl FilePath {
+        /// Creates the backend for the file at the given path, without backups. The file need
+        /// not exist until it is first read.
+        pub fn new<P>(path: P) -> Self
+        where
+            P: AsRef<std::path::Path>,
+        {
+            Self::with_backups(path, 0)
+        }
+
+        /// Creates the backend for the file at the given path, keeping the given number of
+        /// backups of previous saves.
+        pub fn with_backups<P>(path: P, keep: usize) -> Self
+        where
+            P: AsRef<std::path::Path>,
+        {
+            Self {
+                path: path.as_ref().to_owned(),
+                backups: keep,
+                _lock: None,
+                sync_write: true,
+                unsynced: false,
+            }
+        }
+
+        /// Takes an exclusive advisory lock for the file, returning the locked backend. The file
+        /// itself is replaced on every save, so the lock is taken on a sentinel file next to it,
+        /// `db.json.lock` for `db.json`, which is created if needed and left in place. The lock is
+        /// held until the backend and all its clones are dropped.
+        ///
+        /// As with [`File::from_path_locked`](crate::backend::File::from_path_locked), the lock
+        /// only protects against other handles that also lock the file.
+        ///
+        /// # Errors
+        ///
+        /// If the sentinel file could not be opened or created, an error variant is returned. If
+        /// the lock is held elsewhere and `mode` is
+        /// [`LockMode::NonBlocking`](crate::backend::LockMode::NonBlocking), an error of kind
+        /// [`WouldBlock`](std::io::ErrorKind::WouldBlock) is returned.
+        ///
+        /// # Examples
+        ///
+        /// ```
+        /// use koit::backend::{FilePath, LockMode};
+        ///
+        /// #[tokio::main]
+        /// async fn main() -> Result<(), std::io::Error> {
+        ///     let path = std::env::temp_dir().join("koit-file-path-lock-example.json");
+        ///     let first = FilePath::new(&path).locked(LockMode::NonBlocking).await?;
+        ///
+        ///     let err = FilePath::new(&path).locked(LockMode::NonBlocking).await.unwrap_err();
+        ///     assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+        ///
+        ///     drop(first);
+        ///     let _second = FilePath::new(&path).locked(LockMode::NonBlocking).await?;
+        ///
+        ///     Ok(())
+        /// }
+        /// ```
+        pub async fn locked(self, mode: LockMode) -> Result<Self, std::io::Error> {
+            let sentinel = tokio::fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(self.with_suffix("lock"))
+                .await?
+                .into_std()
+                .await;
+            let sentinel = super::file::lock(sentinel, mode).await?;
+            Ok(Self {
+                _lock: Some(std::sync::Arc::new(sentinel)),
+                ..self
+            })
+        }
+
+        /// The number of backups kept.
+        pub fn backups(&self) -> usize {
+            self.backups
+        }
+
+        /// The path of the `n`th most recent backup, counting from 1.
+        pub fn backup_path(&self, n: usize) -> std::path::PathBuf {
+            self.with_suffix(&n.to_string())
+        }
+
+        fn with_suffix(&self, suffix: &str) -> std::path::PathBuf {
+            let mut path = self.path.clone().into_os_string();
+            path.push(".");
+            path.push(suffix);
+            path.into()
+        }
+
+        /// Shift the backups up by one, and link the current file as the newest backup. The
+        /// current file stays in place until it is replaced.
+        async fn rotate(&self) -> Result<(), std::io::Error> {
+            if self.backups == 0 {
+                return Ok(());
+            }
+
+            // Backup numbers can be missing, e.g. after a crash or when the file is young.
+            for n in (1..self.backups).rev() {
+                ignore_not_found(
+                    tokio::fs::rename(self.backup_path(n), self.backup_path(n + 1)).await,
+                )?;
+            }
+            let newest = self.backup_path(1);
+            ignore_not_found(tokio::fs::remove_file(&newest).await)?;
+            match tokio::fs::hard_link(&self.path, &newest).await {
+                Ok(()) => Ok(()),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+                // Not every file system supports hard links.
+                Err(_) => ignore_not_found(tokio::fs::copy(&self.path, &newest).await.map(drop)),
+            }
+        }
+
+        /// Whether to sync the temporary file of the current write. Writes sync unless the
+        /// write was prepared as buffered.
+        fn take_sync_write(&mut self) -> bool {
+            let sync = std::mem::replace(&mut self.sync_write, true);
+            self.unsynced = !sync;
+            sync
+        }
+
+        /// Sync the file if it holds a write that was not synced.
+        async fn sync_unsynced(&mut self) -> Result<(), std::io::Error> {
+            if self.unsynced {
+                tokio::fs::File::open(&self.path).await?.sync_all().await?;
+                self.unsynced = false;
+            }
+            Ok(())
+        }
+
+        /// Replace the file with the temporary file, rotating the backups.
+        async fn replace(&self) -> Result<(), std::io::Error> {
+            self.rotate().await?;
+            tokio::fs::rename(self.with_suffix("tmp"), &self.path).await?;
+            self.prune().await
+        }
+
+        /// Remove the backup just past the number to keep, left over from when more backups
+        /// were kept.
+        async fn prune(&self) -> Result<(), std::io::Error> {
+            if self.backups == 0 {
+                return Ok(());
+            }
+            ignore_not_found(tokio::fs::remove_file(self.backup_path(self.backups + 1)).await)
+        }
+    }
+
+    fn ignore_not_found(result: Result<(), std::io::Error>) -> Result<(), std::io::Error> {
+        match result {
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+
+    #[async_trait]
+    impl Backend for FilePath {
+        type Error = std::io::Error;
+
+        async fn read(&mut self) -> Result<Vec<u8>, Self::Error> {
+            tokio::fs::read(&self.path).await
+        }
+
+        /// Writes and syncs a temporary file, rotates the backups, and renames the temporary
+        /// file over the file. The temporary file is not synced if the write is
+        /// [buffered](crate::Durability::Buffered).
+        async fn write(&mut self, data: Vec<u8>) -> Result<(), Self::Error> {
+            let mut file = tokio::fs::File::create(self.with_suffix("tmp")).await?;
+            file.write_all(&data).await?;
+            if self.take_sync_write() {
+                file.sync_all().await?;
+            }
+            drop(file);
+            self.replace().await
+        }
+
+        /// Returns a writer to the temporary file.
+        fn writer(&mut self) -> Result<Option<Box<dyn std::io::Write + Send + '_>>, Self::Error> {
+            if !can_block() {
+                return Ok(None);
+            }
+            let file = std::fs::File::create(self.with_suffix("tmp"))?;
+            Ok(Some(Box::new(std::io::BufWriter::with_capacity(
+                BUFFER_SIZE,
+                file,
+            ))))
+        }
+
+        /// Syncs the temporary file, rotates the backups, and renames the temporary file over
+        /// the file. The temporary file is not synced if the write is
+        /// [buffered](crate::Durability::Buffered).
+        async fn finish_write(&mut self) -> Result<(), Self::Error> {
+            if self.take_sync_write() {
+                tokio::fs::OpenOptions::new()
+                    .write(true)
+                    .open(self.with_suffix("tmp"))
+                    .await?
+                    .sync_all()
+                    .await?;
+            }
+            self.replace().await
+        }
+
+        fn reader(&mut self) -> Result<Option<Box<dyn std::io::Read + Send + '_>>, Self::Error> {
+            if !can_block() {
+                return Ok(None);
+            }
+            let file = std::fs::File::open(&self.path)?;
+            Ok(Some(Box::new(std::io::BufReader::with_capacity(
+                BUFFER_SIZE,
+                file,
+            ))))
+        }
+
+        /// Syncs the directory, making the renames of the last write durable, and the file if
+        /// the last write was buffered.
+        async fn flush(&mut self) -> Result<(), Self::Error> {
+            self.sync_unsynced().await?;
+            sync_directory(&self.path).await
+        }
+
+        /// The temporary file is synced by unbuffered writes already, so
+        /// [`Durability::Flushed`](crate::Durability::Flushed) only syncs the file after a
+        /// buffered write.
+        async fn flush_with(&mut self, durability: Durability) -> Result<(), Self::Error> {
+            match durability {
+                Durability::Buffered => Ok(()),
+                Durability::Flushed => self.sync_unsynced().await,
+                Durability::FlushedPlusDirSync => self.flush().await,
+            }
+        }
+
+        fn prepare_write(&mut self, durability: Durability) {
+            self.sync_write = durability != Durability::Buffered;
+        }
+
+        /// Fingerprints the size and modification time of the file, or none if the file does
+        /// not exist.
+        async fn fingerprint(&mut self) -> Result<Option<Fingerprint>, Self::Error> {
+            match tokio::fs::metadata(&self.path).await {
+                Ok(metadata) => Ok(Some(Fingerprint::of(&(
+                    metadata.len(),
+                    metadata.modified().ok(),
+                )))),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                Err(err) => Err(err),
+            }
+        }
+
+        fn path(&self) -> Option<&std::path::Path> {
+            Some(&self.path)
+        }
+    }
+}
+
+#[cfg(feature = "blocking-file-backend")]
+mod blocking_file {
+    use std::io::Write;
+
+    use async_trait::async_trait;
+
+    use super::{Backend, Durability, Fingerprint, BUFFER_SIZE};
+
+    /// A backend writing to a file by path, with the same semantics as
+    /// [`FilePath`](crate::backend::FilePath) (without backups): each write goes to a temporary
+    /// file, which is synced to disk unless the write is [buffered](crate::Durability::Buffered),
+    /// and then renamed over the file.
+    ///
+    /// Unlike the other file backends, this one does not need the Tokio runtime. Its I/O is
+    /// performed synchronously on the polling thread, so it works on any executor, but blocks
+    /// it for the duration of each read and write. This suits small files.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use koit::{Database, backend::BlockingFile, format::Json};
+    ///
+    /// fn main() -> koit::Result<()> {
+    ///     let dir = std::env::temp_dir().join("koit-blocking-file-example");
+    ///     let _ = std::fs::remove_dir_all(&dir);
+    ///     std::fs::create_dir_all(&dir).unwrap();
+    ///     let path = dir.join("db.json");
+    ///
+    ///     futures::executor::block_on(async {
+    ///         let db = Database::<Vec<u32>, _, Json>::from_parts(vec![1], BlockingFile::new(&path));
+    ///         db.write(|data| data.push(2)).await;
+    ///         db.save().await?;
+    ///         assert!(!dir.join("db.json.tmp").exists());
+    ///
+    ///         let db = Database::<Vec<u32>, _, Json>::load(BlockingFile::new(&path)).await?;
+    ///         assert_eq!(db.read(|data| data.clone()).await, vec![1, 2]);
+    ///
+    ///         std::fs::write(&path, b"[3]").unwrap();
+    ///         assert_eq!(db.reload_if_changed().await?, Some(vec![1, 2]));
+    ///         assert_eq!(db.read(|data| data.clone()).await, vec![3]);
+    ///
+    ///         Ok(())
+    ///     })
+    /// }
+    /// ```
+    ///
+    /// The same works on `async-std` and `smol`, without any of the default features:
+    ///
+    /// ```
+    /// use koit::{Database, backend::BlockingFile, format::Json};
+    ///
+    /// fn main() -> koit::Result<()> {
+    ///     let dir = std::env::temp_dir().join("koit-blocking-file-executors-example");
+    ///     let _ = std::fs::remove_dir_all(&dir);
+    ///     std::fs::create_dir_all(&dir).unwrap();
+    ///     let path = dir.join("db.json");
+    ///
+    ///     async_std::task::block_on(async {
+    ///         let db = Database::<Vec<u32>, _, Json>::from_parts(vec![1], BlockingFile::new(&path));
+    ///         db.save().await?;
+    ///         Ok::<_, koit::KoitError>(())
+    ///     })?;
+    ///
+    ///     smol::block_on(async {
+    ///         let db = Database::<Vec<u32>, _, Json>::load(BlockingFile::new(&path)).await?;
+    ///         assert_eq!(db.read(|data| data.clone()).await, vec![1]);
+    ///         db.write(|data| data.push(2)).await;
+    ///         db.save().await?;
+    ///         Ok::<_, koit::KoitError>(())
+    ///     })?;
+    ///
+    ///     assert_eq!(std::fs::read_to_string(&path).unwrap(), "[\n  1,\n  2\n]");
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "blocking-file-backend")))]
+    #[derive(Debug, Clone)]
+    pub struct BlockingFile {
+        path: std::path::PathBuf,
+        /// Whether the next write syncs the temporary file, see
+        /// [`Backend::prepare_write`](crate::backend::Backend::prepare_write).
+        sync_write: bool,
+        /// Whether the file holds a write that was not synced.
+        unsynced: bool,
+    }
+
+    impl PartialEq for BlockingFile {
+        fn eq(&self, other: &Self) -> bool {
+            self.path == other.path
+        }
+    }
+
+    impl Eq for BlockingFile {}
+
+    impl BlockingFile {
+        /// Creates the backend for the file at the given path. The file need not exist until it
+        /// is first read.
+        pub fn new<P>(path: P) -> Self
+        where
+            P: AsRef<std::path::Path>,
+        {
+            Self {
+                path: path.as_ref().to_owned(),
+                sync_write: true,
+                unsynced: false,
+            }
+        }
+
+        fn tmp_path(&self) -> std::path::PathBuf {
+            let mut path = self.path.clone().into_os_string();
+            path.push(".tmp");
+            path.into()
+        }
+
+        /// Whether to sync the temporary file of the current write. Writes sync unless the
+        /// write was prepared as buffered.
+        fn take_sync_write(&mut self) -> bool {
+            let sync = std::mem::replace(&mut self.sync_write, true);
+            self.unsynced = !sync;
+            sync
+        }
+
+        /// Sync the file if it holds a write that was not synced.
+        fn sync_unsynced(&mut self) -> Result<(), std::io::Error> {
+            if self.unsynced {
+                std::fs::File::open(&self.path)?.sync_all()?;
+                self.unsynced = false;
+            }
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl Backend for BlockingFile {
+        type Error = std::io::Error;
+
+        async fn read(&mut self) -> Result<Vec<u8>, Self::Error> {
+            std::fs::read(&self.path)
+        }
+
+        /// Writes and syncs a temporary file, and renames it over the file. The temporary file
+        /// is not synced if the write is [buffered](crate::Durability::Buffered).
+        async fn write(&mut self, data: Vec<u8>) -> Result<(), Self::Error> {
+            let tmp = self.tmp_path();
+            let mut file = std::fs::File::create(&tmp)?;
+            file.write_all(&data)?;
+            if self.take_sync_write() {
+                file.sync_all()?;
+            }
+            drop(file);
+            std::fs::rename(&tmp, &self.path)
+        }
+
+        /// Returns a writer to the temporary file.
+        fn writer(&mut self) -> Result<Option<Box<dyn std::io::Write + Send + '_>>, Self::Error> {
+            let file = std::fs::File::create(self.tmp_path())?;
+            Ok(Some(Box::new(std::io::BufWriter::with_capacity(
+                BUFFER_SIZE,
+                file,
+            ))))
+        }
+
+        /// Syncs the temporary file, and renames it over the file. The temporary file is not
+        /// synced if the write is [buffered](crate::Durability::Buffered).
+        async fn finish_write(&mut self) -> Result<(), Self::Error> {
+            let tmp = self.tmp_path();
+            if self.take_sync_write() {
+                std::fs::OpenOptions::new()
+                    .write(true)
+                    .open(&tmp)?
+                    .sync_all()?;
+            }
+            std::fs::rename(&tmp, &self.path)
+        }
+
+        /// Syncs the directory, making the rename of the last write durable, and the file if
+        /// the last write was buffered.
+        async fn flush(&mut self) -> Result<(), Self::Error> {
+            self.sync_unsynced()?;
+            #[cfg(unix)]
+            std::fs::File::open(super::directory(&self.path))?.sync_all()?;
             Ok(())
         }
+
+        /// The temporary file is synced by unbuffered writes already, so
+        /// [`Durability::Flushed`](crate::Durability::Flushed) only syncs the file after a
+        /// buffered write.
+        async fn flush_with(&mut self, durability: Durability) -> Result<(), Self::Error> {
+            match durability {
+                Durability::Buffered => Ok(()),
+                Durability::Flushed => self.sync_unsynced(),
+                Durability::FlushedPlusDirSync => self.flush().await,
+            }
+        }
+
+        fn prepare_write(&mut self, durability: Durability) {
+            self.sync_write = durability != Durability::Buffered;
+        }
+
+        fn reader(&mut self) -> Result<Option<Box<dyn std::io::Read + Send + '_>>, Self::Error> {
+            Ok(Some(Box::new(std::io::BufReader::new(
+                std::fs::File::open(&self.path)?,
+            ))))
+        }
+
+        /// Fingerprints the size and modification time of the file, or none if the file does
+        /// not exist.
+        async fn fingerprint(&mut self) -> Result<Option<Fingerprint>, Self::Error> {
+            match std::fs::metadata(&self.path) {
+                Ok(metadata) => Ok(Some(Fingerprint::of(&(
+                    metadata.len(),
+                    metadata.modified().ok(),
+                )))),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                Err(err) => Err(err),
+            }
+        }
+
+        fn path(&self) -> Option<&std::path::Path> {
+            Some(&self.path)
+        }
     }
 }
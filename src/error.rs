@@ -1,24 +1,62 @@
 use thiserror::Error;
 
 /// The error variants Koit can return.
-/// 
+///
 /// The concrete source error types are the associated errors types
 /// [`Format::Error`](crate::format::Format::Error) and [`Backend::Error`](crate::backend::Backend::Error).
+/// They are erased, so all fallible operations of a [`Database`](crate::Database) return the same
+/// [`Result`](crate::Result), whatever its backend and format. The variant tells which layer
+/// failed; the source error tells why.
+///
+/// # Examples
+///
+/// ```
+/// use koit::{Database, KoitError, backend::Memory, format::Json};
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let result = Database::<u32, Memory, Json>::load(Memory::from(b"cat".to_vec())).await;
+///     let err = result.err().unwrap();
+///
+///     assert!(matches!(err, KoitError::FromFormat(_)));
+///     assert!(err.is_format());
+///     assert!(!err.is_backend());
+///     assert_eq!(err.to_string(), "the format failed to decode the data");
+/// }
+/// ```
 #[derive(Debug, Error)]
 pub enum KoitError {
     /// Data failed to be encoded by the formatter.
-    #[error("the database failed to serialize")]
+    #[error("the format failed to encode the data")]
     ToFormat(#[source] Box<dyn std::error::Error + Send + Sync + 'static>),
     /// Data failed to be decoded by the formatter.
-    #[error("the database failed to deserialize")]
+    #[error("the format failed to decode the data")]
     FromFormat(#[source] Box<dyn std::error::Error + Send + Sync + 'static>),
     /// The backend failed to read bytes.
-    #[error("failed to read from the backend")]
+    #[error("the backend failed to read the data")]
     BackendRead(#[source] Box<dyn std::error::Error + Send + Sync + 'static>),
     /// The backend failed to write bytes.
-    #[error("failed to write to the backend")]
+    #[error("the backend failed to write the data")]
     BackendWrite(#[source] Box<dyn std::error::Error + Send + Sync + 'static>),
     /// The backend failed to be created.
-    #[error("failed to create backend")]
+    #[error("the backend failed to be created")]
     BackendCreation(#[source] Box<dyn std::error::Error + Send + Sync + 'static>),
 }
+
+impl KoitError {
+    /// Whether the backend failed.
+    pub fn is_backend(&self) -> bool {
+        matches!(
+            self,
+            KoitError::BackendRead(_) | KoitError::BackendWrite(_) | KoitError::BackendCreation(_)
+        )
+    }
+
+    /// Whether the format failed.
+    pub fn is_format(&self) -> bool {
+        matches!(self, KoitError::ToFormat(_) | KoitError::FromFormat(_))
+    }
+}
+
+/// A result with a [`KoitError`](crate::KoitError).
+pub type Result<T, E = KoitError> = std::result::Result<T, E>;
@@ -1,5 +1,7 @@
 //! Formats handle transforming structured data to and from bytes for persisting.
 
+use crate::KoitError;
+
 /// Trait implementable by format providers.
 ///
 /// By implementing this trait, a type becomes a marker for the specified format.
@@ -20,6 +22,101 @@ pub trait Format<T>: Sized {
     ///
     /// If the bytes failed to be decoded by the format, an error variant is returned.
     fn from_bytes(data: Vec<u8>) -> Result<T, Self::Error>;
+
+    /// Encode data straight into a writer, used when the backend supports
+    /// [streaming](crate::backend::Backend::writer). Errors of the writer are reported as
+    /// [`BackendWrite`](crate::KoitError::BackendWrite), other errors as
+    /// [`ToFormat`](crate::KoitError::ToFormat).
+    ///
+    /// The default implementation writes the result of
+    /// [`Format::to_bytes`](crate::format::Format::to_bytes). The built-in formats and format
+    /// wrappers encode straight into the writer, producing the same bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use koit::format::{Bincode, Format, Json, PreSized, PreSizedVec};
+    ///
+    /// let data = PreSizedVec((0..100u32).collect());
+    ///
+    /// let mut json = Vec::new();
+    /// <PreSized<Json>>::to_writer(&data, &mut json).unwrap();
+    /// assert_eq!(json, Json::to_bytes(&data).unwrap());
+    /// let decoded: PreSizedVec<u32> = <PreSized<Json>>::from_reader(&mut &json[..]).unwrap();
+    /// assert_eq!(decoded, data);
+    ///
+    /// let mut bincode = Vec::new();
+    /// Bincode::to_writer(&data, &mut bincode).unwrap();
+    /// assert_eq!(bincode, Bincode::to_bytes(&data).unwrap());
+    /// let decoded: PreSizedVec<u32> = Bincode::from_reader(&mut &bincode[..]).unwrap();
+    /// assert_eq!(decoded, data);
+    ///
+    /// // Decoding errors are reported as such, not as failures of the reader.
+    /// let truncated = &json[..json.len() / 2];
+    /// let result: Result<Vec<u32>, _> = Json::from_reader(&mut &truncated[..]);
+    /// assert!(matches!(result, Err(koit::KoitError::FromFormat(_))));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// If the data failed to be encoded by the format, or the writer failed, an error variant is
+    /// returned.
+    fn to_writer(value: &T, writer: &mut dyn std::io::Write) -> Result<(), KoitError> {
+        let bytes = Self::to_bytes(value).map_err(|err| KoitError::ToFormat(err.into()))?;
+        writer
+            .write_all(&bytes)
+            .map_err(|err| KoitError::BackendWrite(err.into()))
+    }
+
+    /// Decode data straight from a reader, used when the backend supports
+    /// [streaming](crate::backend::Backend::reader). Errors of the reader are reported as
+    /// [`BackendRead`](crate::KoitError::BackendRead), other errors as
+    /// [`FromFormat`](crate::KoitError::FromFormat).
+    ///
+    /// The default implementation reads all bytes and decodes them with
+    /// [`Format::from_bytes`](crate::format::Format::from_bytes).
+    ///
+    /// # Errors
+    ///
+    /// If the bytes failed to be decoded by the format, or the reader failed, an error variant
+    /// is returned.
+    fn from_reader(reader: &mut dyn std::io::Read) -> Result<T, KoitError> {
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .map_err(|err| KoitError::BackendRead(err.into()))?;
+        Self::from_bytes(bytes).map_err(|err| KoitError::FromFormat(err.into()))
+    }
+
+    /// Convert bytes to data, decoding into an existing value. This allows the value to keep
+    /// capacity it reserved beforehand, see [`PreSized`](crate::format::PreSized).
+    ///
+    /// The default implementation replaces the value with the result of
+    /// [`Format::from_bytes`](crate::format::Format::from_bytes).
+    ///
+    /// # Errors
+    ///
+    /// If the bytes failed to be decoded by the format, an error variant is returned. The value
+    /// may then be left partially decoded.
+    fn from_bytes_into(data: Vec<u8>, place: &mut T) -> Result<(), Self::Error> {
+        *place = Self::from_bytes(data)?;
+        Ok(())
+    }
+
+    /// Cheaply estimate the number of elements of the sequence or map encoded at the top level of
+    /// the bytes, without decoding them. Returns `None` if no estimate can be made.
+    ///
+    /// The estimate is only a hint: it may be wrong if the bytes do not encode a sequence or map.
+    /// The default implementation makes no estimate.
+    fn probe_elements(_data: &[u8]) -> Option<usize> {
+        None
+    }
+
+    /// Whether [`Format::probe_elements`](crate::format::Format::probe_elements) only reads a
+    /// bounded prefix of the bytes, such as a length prefix, instead of scanning them.
+    /// [`PreSized`](crate::format::PreSized) only probes formats for which this holds. The
+    /// default is `false`.
+    const CHEAP_PROBE: bool = false;
 }
 
 #[cfg(feature = "json-format")]
@@ -28,12 +125,22 @@ pub use self::json::Json;
 #[cfg(feature = "bincode-format")]
 pub use self::bincode::Bincode;
 
+#[cfg(feature = "msgpack-format")]
+pub use self::msgpack::{MessagePack, MessagePackError};
+
+#[cfg(feature = "json-format")]
+pub use self::versioned::{Migration, Migrations, Versioned, VersionedError};
+
+pub use self::presize::{PreSize, PreSized};
+#[cfg(feature = "serde")]
+pub use self::presize::{PreSizedBTreeMap, PreSizedHashMap, PreSizedVec};
 
 #[cfg(feature = "json-format")]
 mod json {
     use serde::{de::DeserializeOwned, Serialize};
 
     use super::Format;
+    use crate::KoitError;
 
     #[cfg_attr(docsrs, doc(cfg(feature = "json-format")))]
     /// A pretty-printed JSON [`Format`](crate::format::Format).
@@ -44,19 +151,93 @@ mod json {
         type Error = serde_json::Error;
 
         fn to_bytes(value: &T) -> Result<Vec<u8>, Self::Error> {
-            Ok(serde_json::to_vec_pretty(value)?)
+            serde_json::to_vec_pretty(value)
         }
         fn from_bytes(data: Vec<u8>) -> Result<T, serde_json::Error> {
-            Ok(serde_json::from_slice(&data)?)
+            serde_json::from_slice(&data)
+        }
+        fn from_bytes_into(data: Vec<u8>, place: &mut T) -> Result<(), Self::Error> {
+            let mut deserializer = serde_json::Deserializer::from_slice(&data);
+            T::deserialize_in_place(&mut deserializer, place)?;
+            deserializer.end()
+        }
+        fn probe_elements(data: &[u8]) -> Option<usize> {
+            probe_elements(data)
+        }
+        fn to_writer(value: &T, writer: &mut dyn std::io::Write) -> Result<(), KoitError> {
+            serde_json::to_writer_pretty(writer, value).map_err(|err| {
+                if err.is_io() {
+                    KoitError::BackendWrite(err.into())
+                } else {
+                    KoitError::ToFormat(err.into())
+                }
+            })
+        }
+        fn from_reader(reader: &mut dyn std::io::Read) -> Result<T, KoitError> {
+            serde_json::from_reader(reader).map_err(|err| {
+                if err.is_io() {
+                    KoitError::BackendRead(err.into())
+                } else {
+                    KoitError::FromFormat(err.into())
+                }
+            })
         }
     }
+
+    /// Count the elements of the top-level JSON array or object by scanning for separators,
+    /// without parsing the elements.
+    fn probe_elements(data: &[u8]) -> Option<usize> {
+        let start = data.iter().position(|byte| !byte.is_ascii_whitespace())?;
+        if !matches!(data[start], b'[' | b'{') {
+            return None;
+        }
+
+        let mut depth = 0usize;
+        let mut in_string = false;
+        let mut escaped = false;
+        let mut empty = true;
+        let mut separators = 0;
+        for &byte in &data[start..] {
+            if in_string {
+                match byte {
+                    _ if escaped => escaped = false,
+                    b'\\' => escaped = true,
+                    b'"' => in_string = false,
+                    _ => {}
+                }
+                continue;
+            }
+            match byte {
+                b'[' | b'{' => {
+                    empty &= depth != 1;
+                    depth += 1;
+                }
+                b']' | b'}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(if empty { 0 } else { separators + 1 });
+                    }
+                }
+                b',' if depth == 1 => separators += 1,
+                b'"' => {
+                    empty &= depth != 1;
+                    in_string = true;
+                }
+                _ if depth == 1 && !byte.is_ascii_whitespace() => empty = false,
+                _ => {}
+            }
+        }
+        None
+    }
 }
 
 #[cfg(feature = "bincode-format")]
 mod bincode {
+    use bincode::Options;
     use serde::{de::DeserializeOwned, Serialize};
 
     use super::Format;
+    use crate::KoitError;
 
     #[cfg_attr(docsrs, doc(cfg(feature = "bincode-format")))]
     /// A Bincode [`Format`](crate::format::Format).
@@ -67,10 +248,1304 @@ mod bincode {
         type Error = bincode::Error;
 
         fn to_bytes(value: &T) -> Result<Vec<u8>, Self::Error> {
-            Ok(bincode::serialize(value)?)
+            bincode::serialize(value)
+        }
+        fn from_bytes(data: Vec<u8>) -> Result<T, Self::Error> {
+            bincode::deserialize(&data)
+        }
+        fn from_bytes_into(data: Vec<u8>, place: &mut T) -> Result<(), Self::Error> {
+            // The options used by `bincode::deserialize`.
+            let options = bincode::DefaultOptions::new()
+                .with_fixint_encoding()
+                .allow_trailing_bytes();
+            let mut deserializer = bincode::Deserializer::from_slice(&data, options);
+            T::deserialize_in_place(&mut deserializer, place)
+        }
+        const CHEAP_PROBE: bool = true;
+
+        fn probe_elements(data: &[u8]) -> Option<usize> {
+            // Sequences and maps are prefixed by their length as a little-endian `u64`.
+            let prefix = std::convert::TryInto::try_into(data.get(..8)?).ok()?;
+            std::convert::TryFrom::try_from(u64::from_le_bytes(prefix)).ok()
+        }
+        fn to_writer(value: &T, writer: &mut dyn std::io::Write) -> Result<(), KoitError> {
+            bincode::serialize_into(writer, value).map_err(|err| match *err {
+                bincode::ErrorKind::Io(err) => KoitError::BackendWrite(err.into()),
+                _ => KoitError::ToFormat(err),
+            })
+        }
+        // Decoding is left to the default, which reads the whole payload first. Decoding from
+        // the reader directly would allocate for length prefixes before checking them against
+        // the payload, which corrupt data can make arbitrarily large.
+    }
+}
+
+#[cfg(feature = "msgpack-format")]
+mod msgpack {
+    use serde::{de::DeserializeOwned, Serialize};
+    use thiserror::Error;
+
+    use super::Format;
+    use crate::KoitError;
+
+    #[cfg_attr(docsrs, doc(cfg(feature = "msgpack-format")))]
+    /// A MessagePack [`Format`](crate::format::Format).
+    ///
+    /// Structs are encoded as maps keyed by field name, so fields can be reordered without
+    /// breaking previously saved data.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    ///
+    /// use koit::format::{Format, MessagePack};
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    /// struct Data {
+    ///     ratio: f64,
+    ///     count: u32,
+    ///     nested: BTreeMap<String, BTreeMap<String, f64>>,
+    /// }
+    ///
+    /// let mut inner = BTreeMap::new();
+    /// inner.insert("whole".to_owned(), 2.0);
+    /// inner.insert("fraction".to_owned(), 0.25);
+    /// let mut nested = BTreeMap::new();
+    /// nested.insert("numbers".to_owned(), inner);
+    ///
+    /// let data = Data { ratio: 1.0, count: 1, nested };
+    /// let bytes = MessagePack::to_bytes(&data).unwrap();
+    /// let decoded: Data = MessagePack::from_bytes(bytes).unwrap();
+    /// assert_eq!(decoded, data);
+    /// assert_eq!(decoded.nested["numbers"]["whole"].to_bits(), 2.0f64.to_bits());
+    ///
+    /// let mut streamed = Vec::new();
+    /// MessagePack::to_writer(&data, &mut streamed).unwrap();
+    /// assert_eq!(streamed, MessagePack::to_bytes(&data).unwrap());
+    /// let decoded: Data = MessagePack::from_reader(&mut &streamed[..]).unwrap();
+    /// assert_eq!(decoded, data);
+    /// ```
+    #[derive(Debug, std::default::Default)]
+    pub struct MessagePack;
+
+    /// The error returned by the [`MessagePack`](crate::format::MessagePack) format.
+    #[cfg_attr(docsrs, doc(cfg(feature = "msgpack-format")))]
+    #[derive(Debug, Error)]
+    pub enum MessagePackError {
+        /// Data failed to be encoded.
+        #[error("failed to encode MessagePack")]
+        Encode(#[from] rmp_serde::encode::Error),
+        /// Bytes failed to be decoded.
+        #[error("failed to decode MessagePack")]
+        Decode(#[from] rmp_serde::decode::Error),
+    }
+
+    impl<T: Serialize + DeserializeOwned> Format<T> for MessagePack {
+        type Error = MessagePackError;
+
+        fn to_bytes(value: &T) -> Result<Vec<u8>, Self::Error> {
+            Ok(rmp_serde::to_vec_named(value)?)
+        }
+        fn from_bytes(data: Vec<u8>) -> Result<T, Self::Error> {
+            Ok(rmp_serde::from_slice(&data)?)
+        }
+        fn from_bytes_into(data: Vec<u8>, place: &mut T) -> Result<(), Self::Error> {
+            let mut deserializer = rmp_serde::Deserializer::from_read_ref(&data);
+            Ok(T::deserialize_in_place(&mut deserializer, place)?)
+        }
+        fn to_writer(value: &T, writer: &mut dyn std::io::Write) -> Result<(), KoitError> {
+            rmp_serde::encode::write_named(writer, value).map_err(|err| match err {
+                rmp_serde::encode::Error::InvalidValueWrite(_) => {
+                    KoitError::BackendWrite(MessagePackError::from(err).into())
+                }
+                err => KoitError::ToFormat(MessagePackError::from(err).into()),
+            })
+        }
+        fn from_reader(reader: &mut dyn std::io::Read) -> Result<T, KoitError> {
+            // Read errors cannot be told apart from truncated data.
+            rmp_serde::from_read(reader)
+                .map_err(|err| KoitError::FromFormat(MessagePackError::from(err).into()))
+        }
+        const CHEAP_PROBE: bool = true;
+
+        fn probe_elements(data: &[u8]) -> Option<usize> {
+            // The length is encoded in the array or map marker.
+            let length = |bytes: &[u8]| {
+                bytes
+                    .iter()
+                    .fold(0usize, |length, byte| (length << 8) | usize::from(*byte))
+            };
+            match *data.first()? {
+                marker @ 0x80..=0x9f => Some(usize::from(marker & 0x0f)),
+                0xdc | 0xde => data.get(1..3).map(length),
+                0xdd | 0xdf => data.get(1..5).map(length),
+                _ => None,
+            }
+        }
+    }
+}
+
+#[cfg(feature = "json-format")]
+mod versioned {
+    use std::marker::PhantomData;
+
+    use serde::{de::DeserializeOwned, Serialize};
+    use serde_json::Value;
+    use thiserror::Error;
+
+    use super::Format;
+    use crate::KoitError;
+
+    const VERSION_KEY: &str = "schema_version";
+    const DATA_KEY: &str = "data";
+
+    /// A migration function, upgrading data of one schema version to the next.
+    #[cfg_attr(docsrs, doc(cfg(feature = "json-format")))]
+    pub type Migration =
+        fn(Value) -> Result<Value, Box<dyn std::error::Error + Send + Sync + 'static>>;
+
+    /// Trait implementable by data types whose schema changes over time.
+    ///
+    /// # Examples
+    ///
+    /// See [`Versioned`](crate::format::Versioned).
+    #[cfg_attr(docsrs, doc(cfg(feature = "json-format")))]
+    pub trait Migrations {
+        /// The chain of migrations, where the migration at index `n` upgrades data of schema
+        /// version `n` to version `n + 1`. The current schema version is the number of
+        /// migrations.
+        const MIGRATIONS: &'static [Migration];
+    }
+
+    /// A [`Format`](crate::format::Format) marker storing a schema version alongside the data,
+    /// using the format `F` to encode both.
+    ///
+    /// The data is stored as `{ "schema_version": n, "data": ... }`. Upon decoding, the
+    /// [migrations](crate::format::Migrations) of the data type are run to upgrade the data to
+    /// the current schema version. Data written before versioning was introduced (anything other
+    /// than such an object) is treated as version 0. Data with a version newer than the current
+    /// version is refused. Upon encoding, the current version is always written.
+    ///
+    /// Migrations operate on [`serde_json::Value`], so `F` must be a self-describing format such
+    /// as [`Json`](crate::format::Json).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use koit::{Database, backend::Memory};
+    /// use koit::format::{Format, Json, Migration, Migrations, Versioned, VersionedError};
+    /// use serde::{Deserialize, Serialize};
+    /// use serde_json::{json, Value};
+    ///
+    /// #[derive(Debug, Default, PartialEq, Deserialize, Serialize)]
+    /// struct Legacy {
+    ///     cats: u64,
+    /// }
+    ///
+    /// #[derive(Debug, Default, PartialEq, Deserialize, Serialize)]
+    /// struct Data {
+    ///     animals: Animals,
+    ///     owner: String,
+    /// }
+    ///
+    /// #[derive(Debug, Default, PartialEq, Deserialize, Serialize)]
+    /// struct Animals {
+    ///     cats: u64,
+    /// }
+    ///
+    /// impl Migrations for Data {
+    ///     const MIGRATIONS: &'static [Migration] = &[
+    ///         |v0| Ok(json!({ "animals": v0 })),
+    ///         |mut v1| {
+    ///             v1["owner"] = Value::from("nobody");
+    ///             Ok(v1)
+    ///         },
+    ///     ];
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), koit::KoitError> {
+    ///     let v0 = Json::to_bytes(&Legacy { cats: 3 }).unwrap();
+    ///     let db: Database<Data, Memory, Versioned<Json>> = Database::from_parts(
+    ///         Data::default(),
+    ///         Memory::from(v0),
+    ///     );
+    ///
+    ///     db.reload().await?;
+    ///     db.read(|data| {
+    ///         assert_eq!(data.animals.cats, 3);
+    ///         assert_eq!(data.owner, "nobody");
+    ///     }).await;
+    ///
+    ///     db.save().await?;
+    ///     let (_data, mut backend) = db.into_parts();
+    ///     let saved: Value = serde_json::from_slice(&backend.take()).unwrap();
+    ///     assert_eq!(saved["schema_version"], 2);
+    ///     assert_eq!(saved["data"]["animals"]["cats"], 3);
+    ///
+    ///     let future = br#"{ "schema_version": 3, "data": {} }"#.to_vec();
+    ///     assert!(matches!(
+    ///         <Versioned<Json> as Format<Data>>::from_bytes(future),
+    ///         Err(VersionedError::TooNew { found: 3, current: 2 })
+    ///     ));
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "json-format")))]
+    #[derive(Debug)]
+    pub struct Versioned<F>(PhantomData<F>);
+
+    impl<F> std::default::Default for Versioned<F> {
+        fn default() -> Self {
+            Self(PhantomData)
+        }
+    }
+
+    /// The error returned by the [`Versioned`](crate::format::Versioned) format.
+    #[cfg_attr(docsrs, doc(cfg(feature = "json-format")))]
+    #[derive(Debug, Error)]
+    pub enum VersionedError<E> {
+        /// The inner format failed.
+        #[error("the inner format failed")]
+        Format(#[source] E),
+        /// The data failed to be converted to or from a [`Value`](serde_json::Value).
+        #[error("failed to convert the data")]
+        Value(#[from] serde_json::Error),
+        /// The stored schema version is not a valid version number.
+        #[error("the stored schema version is invalid")]
+        InvalidVersion,
+        /// The stored schema version is newer than the current version.
+        #[error("schema version {found} is newer than the current schema version {current}")]
+        TooNew {
+            /// The stored version.
+            found: u64,
+            /// The current version.
+            current: u64,
+        },
+        /// A migration failed.
+        #[error("failed to migrate from schema version {version}")]
+        Migration {
+            /// The version the failed migration upgrades from.
+            version: u64,
+            #[source]
+            source: Box<dyn std::error::Error + Send + Sync + 'static>,
+        },
+    }
+
+    /// Split stored data into its schema version and payload.
+    fn split<E>(value: Value) -> Result<(u64, Value), VersionedError<E>> {
+        match value {
+            Value::Object(mut map)
+                if map.len() == 2
+                    && map.contains_key(VERSION_KEY)
+                    && map.contains_key(DATA_KEY) =>
+            {
+                let version = map[VERSION_KEY]
+                    .as_u64()
+                    .ok_or(VersionedError::InvalidVersion)?;
+                Ok((version, map.remove(DATA_KEY).unwrap_or_default()))
+            }
+            legacy => Ok((0, legacy)),
+        }
+    }
+
+    impl<T, F> Format<T> for Versioned<F>
+    where
+        T: Migrations + Serialize + DeserializeOwned,
+        F: Format<Value>,
+    {
+        type Error = VersionedError<F::Error>;
+
+        fn to_bytes(value: &T) -> Result<Vec<u8>, Self::Error> {
+            F::to_bytes(&envelop(value)?).map_err(VersionedError::Format)
         }
         fn from_bytes(data: Vec<u8>) -> Result<T, Self::Error> {
-            Ok(bincode::deserialize(&data)?)
+            unwrap(F::from_bytes(data).map_err(VersionedError::Format)?)
+        }
+        fn to_writer(value: &T, writer: &mut dyn std::io::Write) -> Result<(), KoitError> {
+            let stored =
+                envelop::<T, F::Error>(value).map_err(|err| KoitError::ToFormat(err.into()))?;
+            F::to_writer(&stored, writer)
+        }
+        fn from_reader(reader: &mut dyn std::io::Read) -> Result<T, KoitError> {
+            unwrap::<T, F::Error>(F::from_reader(reader)?)
+                .map_err(|err| KoitError::FromFormat(err.into()))
+        }
+    }
+
+    /// Wrap data in the envelope storing its schema version.
+    fn envelop<T, E>(value: &T) -> Result<Value, VersionedError<E>>
+    where
+        T: Migrations + Serialize,
+    {
+        let mut stored = serde_json::Map::new();
+        stored.insert(VERSION_KEY.to_owned(), T::MIGRATIONS.len().into());
+        stored.insert(DATA_KEY.to_owned(), serde_json::to_value(value)?);
+        Ok(Value::Object(stored))
+    }
+
+    /// Take data out of its envelope, migrating it to the current schema version.
+    fn unwrap<T, E>(stored: Value) -> Result<T, VersionedError<E>>
+    where
+        T: Migrations + DeserializeOwned,
+    {
+        let (version, mut data) = split(stored)?;
+
+        let current = T::MIGRATIONS.len() as u64;
+        if version > current {
+            return Err(VersionedError::TooNew {
+                found: version,
+                current,
+            });
+        }
+
+        for (from, migration) in (version..).zip(&T::MIGRATIONS[version as usize..]) {
+            data = migration(data).map_err(|source| VersionedError::Migration {
+                version: from,
+                source,
+            })?;
+        }
+        Ok(serde_json::from_value(data)?)
+    }
+}
+
+mod presize {
+    use std::marker::PhantomData;
+
+    use super::Format;
+    use crate::KoitError;
+
+    /// Trait implementable by data types that can reserve capacity before being decoded into.
+    ///
+    /// Used by the [`PreSized`](crate::format::PreSized) format. Koit provides implementations for
+    /// [`PreSizedVec`](crate::format::PreSizedVec), [`PreSizedHashMap`](crate::format::PreSizedHashMap)
+    /// and [`PreSizedBTreeMap`](crate::format::PreSizedBTreeMap).
+    ///
+    /// The collections estimate one element per element size of payload, which is exact for
+    /// fixed-size Bincode encodings and overestimates for JSON, but never reserves more bytes
+    /// than the payload holds. Element estimates come from the payload too, so they are capped
+    /// the same way:
+    ///
+    /// ```
+    /// use koit::format::{Bincode, Format, PreSize, PreSized, PreSizedVec};
+    ///
+    /// let mut data = PreSizedVec::<[u64; 32]>::default();
+    /// data.reserve_elements(usize::MAX, 1024);
+    /// assert!(data.capacity() * std::mem::size_of::<[u64; 32]>() <= 1024);
+    ///
+    /// // A corrupt length prefix does not reserve more than the payload.
+    /// let mut bytes = u64::MAX.to_le_bytes().to_vec();
+    /// bytes.extend(vec![0; 1 << 20]);
+    /// let result: Result<PreSizedVec<[u64; 32]>, _> = <PreSized<Bincode>>::from_bytes(bytes);
+    /// assert!(result.is_err());
+    /// ```
+    pub trait PreSize {
+        /// Reserve capacity for data decoded from `payload_len` bytes.
+        fn reserve_for(&mut self, _payload_len: usize) {}
+
+        /// Reserve capacity for `elements` top-level elements, as estimated by
+        /// [`Format::probe_elements`](crate::format::Format::probe_elements) from `payload_len`
+        /// bytes. The estimate is untrusted, and the default implementation falls back to
+        /// [`PreSize::reserve_for`](crate::format::PreSize::reserve_for).
+        fn reserve_elements(&mut self, _elements: usize, payload_len: usize) {
+            self.reserve_for(payload_len)
+        }
+    }
+
+    /// A [`Format`](crate::format::Format) marker wrapping the format `F`, which reserves capacity
+    /// in the data before decoding it.
+    ///
+    /// Upon decoding, a default value of the data is created and given the chance to reserve
+    /// capacity through [`PreSize`](crate::format::PreSize), after which `F` decodes into it.
+    /// Capacity is estimated from the payload length, or from the number of top-level elements
+    /// if `F` can [cheaply](crate::format::Format::CHEAP_PROBE) probe it.
+    ///
+    /// [Bincode](crate::format::Bincode) and [MessagePack](crate::format::MessagePack) read the
+    /// element count from the length prefix. [JSON](crate::format::Json) would have to scan the
+    /// whole payload, which costs more than reserving saves, so only the payload length is used.
+    /// In the `presize` benchmark, loading a map of a million integers was about 1.1 to 1.4
+    /// times as fast for JSON, and 1.3 to 1.9 times as fast for Bincode.
+    ///
+    /// Encoding is left to `F`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use koit::format::{Bincode, Format, PreSized, PreSizedHashMap};
+    ///
+    /// let mut data = PreSizedHashMap::default();
+    /// for n in 0..1000u32 {
+    ///     data.insert(n.to_string(), n);
+    /// }
+    ///
+    /// let bytes = Bincode::to_bytes(&data).unwrap();
+    /// let decoded: PreSizedHashMap<String, u32> = <PreSized<Bincode>>::from_bytes(bytes).unwrap();
+    /// assert_eq!(decoded, data);
+    /// assert!(decoded.capacity() >= 1000);
+    /// ```
+    #[derive(Debug)]
+    pub struct PreSized<F>(PhantomData<F>);
+
+    impl<F> std::default::Default for PreSized<F> {
+        fn default() -> Self {
+            Self(PhantomData)
+        }
+    }
+
+    impl<T, F> Format<T> for PreSized<F>
+    where
+        T: PreSize + Default,
+        F: Format<T>,
+    {
+        type Error = F::Error;
+
+        fn to_bytes(value: &T) -> Result<Vec<u8>, Self::Error> {
+            F::to_bytes(value)
+        }
+        fn from_bytes(data: Vec<u8>) -> Result<T, Self::Error> {
+            let mut value = T::default();
+            match F::CHEAP_PROBE.then(|| F::probe_elements(&data)).flatten() {
+                Some(elements) => value.reserve_elements(elements, data.len()),
+                None => value.reserve_for(data.len()),
+            }
+            F::from_bytes_into(data, &mut value)?;
+            Ok(value)
+        }
+
+        const CHEAP_PROBE: bool = F::CHEAP_PROBE;
+
+        fn probe_elements(data: &[u8]) -> Option<usize> {
+            F::probe_elements(data)
+        }
+        fn to_writer(value: &T, writer: &mut dyn std::io::Write) -> Result<(), KoitError> {
+            F::to_writer(value, writer)
+        }
+        // Reading is left to the default: sizing needs the whole payload.
+    }
+
+    #[cfg(feature = "serde")]
+    pub use self::collections::{PreSizedBTreeMap, PreSizedHashMap, PreSizedVec};
+
+    #[cfg(feature = "serde")]
+    mod collections {
+        use std::collections::{hash_map::RandomState, BTreeMap, HashMap};
+        use std::fmt;
+        use std::hash::{BuildHasher, Hash};
+        use std::marker::PhantomData;
+
+        use serde::de::{Deserialize, Deserializer, MapAccess, Visitor};
+        use serde::{Serialize, Serializer};
+
+        use super::PreSize;
+
+        /// Cap on capacity reserved from untrusted size hints, as serde itself does.
+        const MAX_HINT: usize = 4096;
+
+        /// The number of elements of type `T` fitting in the memory taken by `payload_len` bytes.
+        fn fitting<T>(payload_len: usize) -> usize {
+            payload_len / std::mem::size_of::<T>().max(1)
+        }
+
+        /// A [`Vec`](std::vec::Vec) reserving its elements before being decoded into. See
+        /// [`PreSize`](crate::format::PreSize).
+        #[derive(Debug, Clone)]
+        pub struct PreSizedVec<T>(pub Vec<T>);
+
+        /// A [`HashMap`](std::collections::HashMap) reserving its entries before being decoded
+        /// into. See [`PreSize`](crate::format::PreSize).
+        #[derive(Debug, Clone)]
+        pub struct PreSizedHashMap<K, V, S = RandomState>(pub HashMap<K, V, S>);
+
+        /// A [`BTreeMap`](std::collections::BTreeMap) for use with
+        /// [`PreSized`](crate::format::PreSized).
+        ///
+        /// B-trees have no capacity to reserve. Instead, decoded entries are collected and the
+        /// tree is built in bulk, which is faster than inserting entries one by one.
+        #[derive(Debug, Clone)]
+        pub struct PreSizedBTreeMap<K, V>(pub BTreeMap<K, V>);
+
+        /// Implement the traits making the newtypes stand in for their inner collection.
+        macro_rules! forward {
+            ($name:ident<$($param:ident),*>($inner:ty)) => {
+                impl<$($param),*> std::default::Default for $name<$($param),*>
+                where
+                    $inner: std::default::Default,
+                {
+                    fn default() -> Self {
+                        Self(std::default::Default::default())
+                    }
+                }
+
+                impl<$($param),*> PartialEq for $name<$($param),*>
+                where
+                    $inner: PartialEq,
+                {
+                    fn eq(&self, other: &Self) -> bool {
+                        self.0 == other.0
+                    }
+                }
+
+                impl<$($param),*> Eq for $name<$($param),*> where $inner: Eq {}
+
+                impl<$($param),*> From<$inner> for $name<$($param),*> {
+                    fn from(inner: $inner) -> Self {
+                        Self(inner)
+                    }
+                }
+
+                impl<$($param),*> std::ops::Deref for $name<$($param),*> {
+                    type Target = $inner;
+
+                    fn deref(&self) -> &$inner {
+                        &self.0
+                    }
+                }
+
+                impl<$($param),*> std::ops::DerefMut for $name<$($param),*> {
+                    fn deref_mut(&mut self) -> &mut $inner {
+                        &mut self.0
+                    }
+                }
+
+                impl<$($param),*> Serialize for $name<$($param),*>
+                where
+                    $inner: Serialize,
+                {
+                    fn serialize<Ser>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error>
+                    where
+                        Ser: Serializer,
+                    {
+                        self.0.serialize(serializer)
+                    }
+                }
+            };
+        }
+
+        forward!(PreSizedVec<T>(Vec<T>));
+        forward!(PreSizedHashMap<K, V, S>(HashMap<K, V, S>));
+        forward!(PreSizedBTreeMap<K, V>(BTreeMap<K, V>));
+
+        impl<T> PreSize for PreSizedVec<T> {
+            fn reserve_for(&mut self, payload_len: usize) {
+                self.0.reserve(fitting::<T>(payload_len));
+            }
+
+            fn reserve_elements(&mut self, elements: usize, payload_len: usize) {
+                self.0.reserve(elements.min(fitting::<T>(payload_len)));
+            }
+        }
+
+        impl<'de, T> Deserialize<'de> for PreSizedVec<T>
+        where
+            T: Deserialize<'de>,
+        {
+            fn deserialize<De: Deserializer<'de>>(deserializer: De) -> Result<Self, De::Error> {
+                Vec::deserialize(deserializer).map(Self)
+            }
+            fn deserialize_in_place<De: Deserializer<'de>>(
+                deserializer: De,
+                place: &mut Self,
+            ) -> Result<(), De::Error> {
+                // Decoding into a vector keeps its capacity.
+                Vec::deserialize_in_place(deserializer, &mut place.0)
+            }
+        }
+
+        impl<K, V, S> PreSize for PreSizedHashMap<K, V, S>
+        where
+            K: Eq + Hash,
+            S: BuildHasher,
+        {
+            fn reserve_for(&mut self, payload_len: usize) {
+                self.0.reserve(fitting::<(K, V)>(payload_len));
+            }
+
+            fn reserve_elements(&mut self, elements: usize, payload_len: usize) {
+                self.0.reserve(elements.min(fitting::<(K, V)>(payload_len)));
+            }
+        }
+
+        impl<'de, K, V, S> Deserialize<'de> for PreSizedHashMap<K, V, S>
+        where
+            K: Deserialize<'de> + Eq + Hash,
+            V: Deserialize<'de>,
+            S: BuildHasher + Default,
+        {
+            fn deserialize<De: Deserializer<'de>>(deserializer: De) -> Result<Self, De::Error> {
+                HashMap::deserialize(deserializer).map(Self)
+            }
+            fn deserialize_in_place<De: Deserializer<'de>>(
+                deserializer: De,
+                place: &mut Self,
+            ) -> Result<(), De::Error> {
+                struct InPlace<'a, K, V, S>(&'a mut HashMap<K, V, S>);
+
+                impl<'de, K, V, S> Visitor<'de> for InPlace<'_, K, V, S>
+                where
+                    K: Deserialize<'de> + Eq + Hash,
+                    V: Deserialize<'de>,
+                    S: BuildHasher,
+                {
+                    type Value = ();
+
+                    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                        formatter.write_str("a map")
+                    }
+
+                    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+                        self.0.clear();
+                        self.0.reserve(map.size_hint().unwrap_or(0).min(MAX_HINT));
+                        while let Some((key, value)) = map.next_entry()? {
+                            self.0.insert(key, value);
+                        }
+                        Ok(())
+                    }
+                }
+
+                deserializer.deserialize_map(InPlace(&mut place.0))
+            }
+        }
+
+        impl<K, V> PreSize for PreSizedBTreeMap<K, V> {}
+
+        impl<'de, K, V> Deserialize<'de> for PreSizedBTreeMap<K, V>
+        where
+            K: Deserialize<'de> + Ord,
+            V: Deserialize<'de>,
+        {
+            fn deserialize<De: Deserializer<'de>>(deserializer: De) -> Result<Self, De::Error> {
+                struct Bulk<K, V>(PhantomData<(K, V)>);
+
+                impl<'de, K, V> Visitor<'de> for Bulk<K, V>
+                where
+                    K: Deserialize<'de> + Ord,
+                    V: Deserialize<'de>,
+                {
+                    type Value = BTreeMap<K, V>;
+
+                    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                        formatter.write_str("a map")
+                    }
+
+                    fn visit_map<A: MapAccess<'de>>(
+                        self,
+                        mut map: A,
+                    ) -> Result<BTreeMap<K, V>, A::Error> {
+                        let mut entries =
+                            Vec::with_capacity(map.size_hint().unwrap_or(0).min(MAX_HINT));
+                        while let Some(entry) = map.next_entry()? {
+                            entries.push(entry);
+                        }
+                        // Later duplicate keys win, as they would when inserting.
+                        Ok(entries.into_iter().collect())
+                    }
+                }
+
+                deserializer.deserialize_map(Bulk(PhantomData)).map(Self)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "testing")]
+#[cfg_attr(docsrs, doc(cfg(feature = "testing")))]
+pub mod conformance {
+    //! A conformance kit for [`Format`](crate::format::Format) implementations.
+    //!
+    //! [`check`](crate::format::conformance::check) runs a format through the expectations koit
+    //! has of it, using values of a test data type implementing
+    //! [`Generate`](crate::format::conformance::Generate):
+    //!
+    //! - every value round-trips through [`Format::to_bytes`](crate::format::Format::to_bytes)
+    //!   and [`Format::from_bytes`](crate::format::Format::from_bytes), and through the streaming
+    //!   methods, which encode the same bytes;
+    //! - encodings are not empty, as no bytes means an empty backend, and element
+    //!   [estimates](crate::format::Format::probe_elements) do not exceed the payload length;
+    //! - encoding is deterministic, if claimed;
+    //! - decoding empty input fails, unless allowed;
+    //! - decoding garbage (random bytes, truncated and corrupted encodings) does not panic;
+    //! - errors are reported as the right [`KoitError`](crate::KoitError) variant: failures of a
+    //!   writer as [`BackendWrite`](crate::KoitError::BackendWrite), and undecodable data as
+    //!   [`FromFormat`](crate::KoitError::FromFormat), even when streamed from a reader. Error
+    //!   messages are not empty.
+    //!
+    //! Panics of the format are caught and reported as failures.
+    //!
+    //! This module is only available with the `testing` feature.
+
+    use std::collections::BTreeMap;
+    use std::fmt::Debug;
+
+    use super::Format;
+    use crate::testing::Generator;
+    use crate::KoitError;
+
+    /// Trait implementable by test data types, providing values to check formats with.
+    pub trait Generate: Sized {
+        /// Values that are always checked, such as extremes. The default implementation has
+        /// none.
+        fn edge_cases() -> Vec<Self> {
+            Vec::new()
+        }
+
+        /// Generate a value.
+        fn generate(generator: &mut Generator) -> Self;
+    }
+
+    /// The configuration of a [conformance check](crate::format::conformance::check_with).
+    #[derive(Debug, Clone)]
+    pub struct Config {
+        seed: u64,
+        generated: usize,
+        fuzz_cases: usize,
+        deterministic: bool,
+        allow_empty: bool,
+    }
+
+    impl std::default::Default for Config {
+        /// Checks 64 generated values and 256 garbage inputs, with seed 0. Determinism is not
+        /// checked, and decoding empty input must fail.
+        fn default() -> Self {
+            Self {
+                seed: 0,
+                generated: 64,
+                fuzz_cases: 256,
+                deterministic: false,
+                allow_empty: false,
+            }
+        }
+    }
+
+    impl Config {
+        /// Seed the generation of values and garbage.
+        pub fn seed(mut self, seed: u64) -> Self {
+            self.seed = seed;
+            self
+        }
+
+        /// The number of values to generate, besides the edge cases.
+        pub fn generated(mut self, generated: usize) -> Self {
+            self.generated = generated;
+            self
+        }
+
+        /// The number of garbage inputs to decode.
+        pub fn fuzz_cases(mut self, fuzz_cases: usize) -> Self {
+            self.fuzz_cases = fuzz_cases;
+            self
+        }
+
+        /// Whether the format claims to always encode a value to the same bytes.
+        pub fn deterministic(mut self, deterministic: bool) -> Self {
+            self.deterministic = deterministic;
+            self
+        }
+
+        /// Whether the format may decode empty input to a value.
+        pub fn allow_empty(mut self, allow_empty: bool) -> Self {
+            self.allow_empty = allow_empty;
+            self
+        }
+    }
+
+    /// A failed expectation.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Failure {
+        check: &'static str,
+        message: String,
+    }
+
+    impl Failure {
+        /// The expectation that failed: `"round-trip"`, `"streaming"`, `"size"`,
+        /// `"determinism"`, `"empty input"`, `"garbage"` or `"errors"`.
+        pub fn check(&self) -> &'static str {
+            self.check
+        }
+
+        /// What went wrong.
+        pub fn message(&self) -> &str {
+            &self.message
+        }
+    }
+
+    impl std::fmt::Display for Failure {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{}: {}", self.check, self.message)
+        }
+    }
+
+    /// The failed expectations of a [conformance check](crate::format::conformance::check).
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Failures(Vec<Failure>);
+
+    impl Failures {
+        /// The failed expectations, in the order they were found.
+        pub fn failures(&self) -> &[Failure] {
+            &self.0
+        }
+    }
+
+    impl std::fmt::Display for Failures {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "the format failed {} conformance checks", self.0.len())?;
+            for failure in &self.0 {
+                write!(f, "\n- {}", failure)?;
+            }
+            Ok(())
+        }
+    }
+
+    impl std::error::Error for Failures {}
+
+    /// Check a format with the [default configuration](crate::format::conformance::Config).
+    ///
+    /// # Errors
+    ///
+    /// If the format failed any expectation, all failures are returned.
+    pub fn check<F, T>() -> Result<(), Failures>
+    where
+        F: Format<T>,
+        T: Generate + PartialEq + Debug,
+    {
+        check_with::<F, T>(&Config::default())
+    }
+
+    /// Check a format. See the [module documentation](crate::format::conformance).
+    ///
+    /// # Examples
+    ///
+    /// The built-in formats conform:
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    ///
+    /// use koit::format::{Bincode, Json, Migrations, PreSized, PreSizedVec, Versioned};
+    /// use koit::format::conformance::{Config, Generate, check_with};
+    /// use koit::testing::Generator;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    /// struct Record {
+    ///     name: String,
+    ///     tags: BTreeMap<String, Option<f64>>,
+    ///     counts: Vec<(u64, i32)>,
+    /// }
+    ///
+    /// impl Generate for Record {
+    ///     fn generate(generator: &mut Generator) -> Self {
+    ///         Record {
+    ///             name: Generate::generate(generator),
+    ///             tags: Generate::generate(generator),
+    ///             counts: Generate::generate(generator),
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// impl Migrations for Record {
+    ///     const MIGRATIONS: &'static [koit::format::Migration] = &[];
+    /// }
+    ///
+    /// let config = Config::default().deterministic(true);
+    /// check_with::<Json, Vec<Record>>(&config).unwrap();
+    /// check_with::<Bincode, Vec<Record>>(&config).unwrap();
+    /// check_with::<Versioned<Json>, Record>(&config).unwrap();
+    /// check_with::<PreSized<Json>, PreSizedVec<Record>>(&config).unwrap();
+    /// #[cfg(feature = "msgpack-format")]
+    /// check_with::<koit::format::MessagePack, Vec<Record>>(&config).unwrap();
+    /// ```
+    ///
+    /// Failures are reported precisely:
+    ///
+    /// ```
+    /// use koit::format::{Format, conformance::check};
+    ///
+    /// /// Little-endian bytes, without checking the length.
+    /// struct Raw;
+    ///
+    /// impl Format<u32> for Raw {
+    ///     type Error = std::io::Error;
+    ///
+    ///     fn to_bytes(value: &u32) -> Result<Vec<u8>, Self::Error> {
+    ///         Ok(value.to_le_bytes().to_vec())
+    ///     }
+    ///     fn from_bytes(data: Vec<u8>) -> Result<u32, Self::Error> {
+    ///         Ok(u32::from_le_bytes([data[0], data[1], data[2], data[3]]))
+    ///     }
+    /// }
+    ///
+    /// let failures = check::<Raw, u32>().unwrap_err();
+    /// assert!(failures.failures().iter().any(|failure| failure.check() == "empty input"));
+    /// assert!(failures.failures().iter().any(|failure| failure.check() == "garbage"));
+    /// assert!(!failures.failures().iter().any(|failure| failure.check() == "round-trip"));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// If the format failed any expectation, all failures are returned.
+    pub fn check_with<F, T>(config: &Config) -> Result<(), Failures>
+    where
+        F: Format<T>,
+        T: Generate + PartialEq + Debug,
+    {
+        let mut failures = Vec::new();
+        let mut fail = |check, message| failures.push(Failure { check, message });
+        let mut generator = Generator::seeded(config.seed);
+
+        let mut values = T::edge_cases();
+        values.extend((0..config.generated).map(|_| T::generate(&mut generator)));
+
+        let mut encodings = Vec::new();
+        for value in &values {
+            let shown = show(value);
+            let bytes = match guard(|| F::to_bytes(value)) {
+                Ok(Ok(bytes)) => bytes,
+                Ok(Err(err)) => {
+                    fail("round-trip", format!("failed to encode {}: {}", shown, err));
+                    continue;
+                }
+                Err(panic) => {
+                    fail(
+                        "round-trip",
+                        format!("panicked encoding {}: {}", shown, panic),
+                    );
+                    continue;
+                }
+            };
+
+            match guard(|| F::from_bytes(bytes.clone())) {
+                Ok(Ok(decoded)) if &decoded == value => {}
+                Ok(Ok(decoded)) => fail(
+                    "round-trip",
+                    format!("{} decoded as {}", shown, show(&decoded)),
+                ),
+                Ok(Err(err)) => fail(
+                    "round-trip",
+                    format!("failed to decode the encoding of {}: {}", shown, err),
+                ),
+                Err(panic) => fail(
+                    "round-trip",
+                    format!("panicked decoding the encoding of {}: {}", shown, panic),
+                ),
+            }
+
+            let mut streamed = Vec::new();
+            match guard(|| F::to_writer(value, &mut streamed)) {
+                Ok(Ok(())) if streamed == bytes => {}
+                Ok(Ok(())) => fail(
+                    "streaming",
+                    format!("to_writer encoded {} differently from to_bytes", shown),
+                ),
+                Ok(Err(err)) => fail("streaming", format!("failed to stream {}: {}", shown, err)),
+                Err(panic) => fail(
+                    "streaming",
+                    format!("panicked streaming {}: {}", shown, panic),
+                ),
+            }
+            match guard(|| F::from_reader(&mut &bytes[..])) {
+                Ok(Ok(decoded)) if &decoded == value => {}
+                Ok(Ok(decoded)) => fail(
+                    "streaming",
+                    format!("{} streamed back as {}", shown, show(&decoded)),
+                ),
+                Ok(Err(err)) => fail(
+                    "streaming",
+                    format!("failed to stream back the encoding of {}: {}", shown, err),
+                ),
+                Err(panic) => fail(
+                    "streaming",
+                    format!(
+                        "panicked streaming back the encoding of {}: {}",
+                        shown, panic
+                    ),
+                ),
+            }
+
+            if bytes.is_empty() {
+                fail(
+                    "size",
+                    format!("{} encoded to no bytes, like an empty backend", shown),
+                );
+            } else {
+                match guard(|| F::to_writer(value, &mut Broken)) {
+                    Ok(Err(KoitError::BackendWrite(_))) => {}
+                    Ok(Err(err)) => fail(
+                        "errors",
+                        format!("a failing writer was reported as {:?}", err),
+                    ),
+                    Ok(Ok(())) => fail(
+                        "errors",
+                        format!("streaming {} to a failing writer succeeded", shown),
+                    ),
+                    Err(panic) => fail(
+                        "errors",
+                        format!("panicked streaming to a failing writer: {}", panic),
+                    ),
+                }
+            }
+            match guard(|| F::probe_elements(&bytes)) {
+                Ok(Some(elements)) if elements > bytes.len() => fail(
+                    "size",
+                    format!(
+                        "estimated {} elements in the {} bytes encoding {}",
+                        elements,
+                        bytes.len(),
+                        shown
+                    ),
+                ),
+                Ok(_) => {}
+                Err(panic) => fail(
+                    "size",
+                    format!("panicked estimating the elements of {}: {}", shown, panic),
+                ),
+            }
+
+            if config.deterministic {
+                match guard(|| F::to_bytes(value)) {
+                    Ok(Ok(again)) if again == bytes => {}
+                    _ => fail(
+                        "determinism",
+                        format!("{} did not encode the same way twice", shown),
+                    ),
+                }
+            }
+
+            encodings.push(bytes);
+        }
+
+        match guard(|| F::from_bytes(Vec::new())) {
+            Ok(Ok(decoded)) if !config.allow_empty => fail(
+                "empty input",
+                format!("decoded no bytes as {}", show(&decoded)),
+            ),
+            Ok(_) => {}
+            Err(panic) => fail(
+                "empty input",
+                format!("panicked decoding no bytes: {}", panic),
+            ),
+        }
+
+        for case in 0..config.fuzz_cases {
+            let input = garbage(&mut generator, &encodings, case);
+            match guard(|| F::from_bytes(input.clone())) {
+                Ok(Err(err)) if err.to_string().is_empty() => fail(
+                    "errors",
+                    format!("the error decoding {:?} has no message", input),
+                ),
+                Ok(_) => {}
+                Err(panic) => fail(
+                    "garbage",
+                    format!("panicked decoding {:?}: {}", input, panic),
+                ),
+            }
+            match guard(|| F::from_reader(&mut &input[..])) {
+                Ok(Ok(_)) | Ok(Err(KoitError::FromFormat(_))) => {}
+                Ok(Err(err)) => fail(
+                    "errors",
+                    format!(
+                        "streaming {:?} from a working reader was reported as {:?}",
+                        input, err
+                    ),
+                ),
+                Err(panic) => fail(
+                    "garbage",
+                    format!("panicked streaming {:?}: {}", input, panic),
+                ),
+            }
+        }
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(Failures(failures))
+        }
+    }
+
+    /// Random bytes, or a truncated or corrupted encoding.
+    fn garbage(generator: &mut Generator, encodings: &[Vec<u8>], case: usize) -> Vec<u8> {
+        let encoding = match encodings.len() {
+            0 => None,
+            len => Some(&encodings[generator.below(len)]),
+        };
+        match (case % 3, encoding) {
+            (1, Some(encoding)) => encoding[..generator.below(encoding.len())].to_vec(),
+            (2, Some(encoding)) if !encoding.is_empty() => {
+                let mut corrupted = encoding.clone();
+                let index = generator.below(corrupted.len());
+                corrupted[index] ^= 1 << generator.below(8);
+                corrupted
+            }
+            _ => (0..generator.below(64))
+                .map(|_| generator.next_u64() as u8)
+                .collect(),
+        }
+    }
+
+    /// Run `task`, catching its panic.
+    fn guard<R>(task: impl FnOnce() -> R) -> Result<R, String> {
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(task)).map_err(|panic| {
+            match (panic.downcast_ref::<&str>(), panic.downcast_ref::<String>()) {
+                (Some(message), _) => (*message).to_owned(),
+                (_, Some(message)) => message.clone(),
+                _ => "a panic".to_owned(),
+            }
+        })
+    }
+
+    /// Show a value in a message, abbreviated.
+    fn show<T: Debug>(value: &T) -> String {
+        const MAX_CHARS: usize = 200;
+
+        let shown = format!("{:?}", value);
+        match shown.char_indices().nth(MAX_CHARS) {
+            Some((end, _)) => format!("{}...", &shown[..end]),
+            None => shown,
+        }
+    }
+
+    /// A writer that always fails.
+    struct Broken;
+
+    impl std::io::Write for Broken {
+        fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("the writer is broken"))
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Err(std::io::Error::other("the writer is broken"))
+        }
+    }
+
+    macro_rules! generate_integers {
+        ($($ty:ty),*) => {
+            $(
+                impl Generate for $ty {
+                    fn edge_cases() -> Vec<Self> {
+                        vec![0, 1, <$ty>::MIN, <$ty>::MAX]
+                    }
+                    fn generate(generator: &mut Generator) -> Self {
+                        generator.next_u64() as $ty
+                    }
+                }
+            )*
+        };
+    }
+
+    generate_integers!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+    impl Generate for bool {
+        fn edge_cases() -> Vec<Self> {
+            vec![false, true]
+        }
+        fn generate(generator: &mut Generator) -> Self {
+            generator.below(2) == 1
+        }
+    }
+
+    impl Generate for f64 {
+        fn edge_cases() -> Vec<Self> {
+            vec![0.0, 1.0, -1.5]
+        }
+        /// Generates finite values with few significant digits, which text formats round-trip
+        /// exactly.
+        fn generate(generator: &mut Generator) -> Self {
+            f64::from(generator.next_u64() as i32) / 16.0
+        }
+    }
+
+    /// Characters that tend to need escaping or multiple bytes.
+    const CHARS: &[char] = &[
+        'a', 'Z', '0', ' ', '"', '\\', '\n', '\t', '\0', 'é', '€', '😀',
+    ];
+
+    impl Generate for char {
+        fn edge_cases() -> Vec<Self> {
+            CHARS.to_vec()
+        }
+        fn generate(generator: &mut Generator) -> Self {
+            CHARS[generator.below(CHARS.len())]
+        }
+    }
+
+    impl Generate for String {
+        fn edge_cases() -> Vec<Self> {
+            vec![String::new(), CHARS.iter().collect()]
+        }
+        fn generate(generator: &mut Generator) -> Self {
+            (0..generator.below(12))
+                .map(|_| char::generate(generator))
+                .collect()
+        }
+    }
+
+    impl<T: Generate> Generate for Option<T> {
+        fn edge_cases() -> Vec<Self> {
+            vec![None]
+        }
+        fn generate(generator: &mut Generator) -> Self {
+            match generator.below(4) {
+                0 => None,
+                _ => Some(T::generate(generator)),
+            }
+        }
+    }
+
+    impl<T: Generate> Generate for Vec<T> {
+        fn edge_cases() -> Vec<Self> {
+            vec![Vec::new(), T::edge_cases()]
+        }
+        fn generate(generator: &mut Generator) -> Self {
+            (0..generator.below(6))
+                .map(|_| T::generate(generator))
+                .collect()
+        }
+    }
+
+    impl<K: Generate + Ord, V: Generate> Generate for BTreeMap<K, V> {
+        fn edge_cases() -> Vec<Self> {
+            vec![BTreeMap::new()]
+        }
+        fn generate(generator: &mut Generator) -> Self {
+            (0..generator.below(6))
+                .map(|_| (K::generate(generator), V::generate(generator)))
+                .collect()
+        }
+    }
+
+    impl<A: Generate, B: Generate> Generate for (A, B) {
+        fn generate(generator: &mut Generator) -> Self {
+            (A::generate(generator), B::generate(generator))
+        }
+    }
+
+    impl<A: Generate, B: Generate, C: Generate> Generate for (A, B, C) {
+        fn generate(generator: &mut Generator) -> Self {
+            (
+                A::generate(generator),
+                B::generate(generator),
+                C::generate(generator),
+            )
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    impl<T: Generate> Generate for super::PreSizedVec<T> {
+        fn edge_cases() -> Vec<Self> {
+            Vec::edge_cases().into_iter().map(Self).collect()
+        }
+        fn generate(generator: &mut Generator) -> Self {
+            Self(Vec::generate(generator))
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    impl<K: Generate + Ord, V: Generate> Generate for super::PreSizedBTreeMap<K, V> {
+        fn edge_cases() -> Vec<Self> {
+            BTreeMap::edge_cases().into_iter().map(Self).collect()
+        }
+        fn generate(generator: &mut Generator) -> Self {
+            Self(BTreeMap::generate(generator))
         }
     }
 }
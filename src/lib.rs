@@ -16,7 +16,8 @@
 //!
 //! #[tokio::main]
 //! async fn main() -> Result<(), Box<dyn std::error::Error>> {
-//!     let db = FileDatabase::<Data, Json>::load_from_path_or_default("./db.json").await?;
+//!     let path = std::env::temp_dir().join("koit-example-db.json");
+//!     let db = FileDatabase::<Data, Json>::load_from_path_or_default(&path).await?;
 //!   
 //!     db.write(|data| {
 //!         data.cats = 10;
@@ -26,6 +27,7 @@
 //!     assert_eq!(db.read(|data| data.cats + data.yaks).await, 42);
 //!
 //!     db.save().await?;
+//!     # std::fs::remove_file(&path)?;
 //!
 //!     Ok(())
 //! }
@@ -37,23 +39,56 @@
 //! and [Bincode](crate::format::Bincode) formatters. You can also define your own storage
 //! [format](crate::format) or [backend](crate::backend).
 //!
-//! Note that the file-backed database requires the Tokio 0.3 runtime to function.
+//! A MessagePack formatter is available behind the `msgpack-format` feature.
+//!
+//! Note that the file-backed database requires the Tokio 0.3 runtime to function. The
+//! [`BlockingFile`](crate::backend::BlockingFile) backend, behind the `blocking-file-backend`
+//! feature, works on any executor.
 
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
 use std::future::Future;
 use std::marker::PhantomData;
-use tokio::sync::{Mutex, RwLock};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use tokio::sync::{watch, Mutex, RwLock};
 
 mod error;
-pub use error::KoitError;
+pub use error::{KoitError, KoitError as Error, Result};
 
 pub mod backend;
-pub use backend::Backend;
+pub use backend::{Backend, Durability};
 
 pub mod format;
 pub use format::Format;
 
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+pub mod versioned;
+
+pub mod repair;
+
+mod quiesce;
+pub use quiesce::{QuiesceGuard, QuiesceMode};
+
+#[cfg(feature = "autosave")]
+mod autosave;
+#[cfg(feature = "autosave")]
+pub use autosave::Autosave;
+
+#[cfg(feature = "testing")]
+#[cfg_attr(docsrs, doc(cfg(feature = "testing")))]
+pub mod testing;
+
+/// The outcome of a successful [save](crate::Database::save).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Saved {
+    /// The data was written to the backend.
+    Persisted,
+    /// The database is degraded and keeps its data in memory only, see
+    /// [`Database::set_degrade_on_read_only`](crate::Database::set_degrade_on_read_only).
+    Degraded,
+}
+
 /// The Koit database.
 ///
 /// The database provides reading, writing, saving and reloading functionality.
@@ -64,7 +99,19 @@ pub use format::Format;
 #[derive(Debug)]
 pub struct Database<D, B, F> {
     data: RwLock<D>,
+    // Held by writers before write-locking the data, so quiescing can block writes without
+    // read-locking the data, which would queue readers behind the blocked writers.
+    writes: Mutex<()>,
     backend: Mutex<B>,
+    dirty: AtomicBool,
+    degrade_on_read_only: AtomicBool,
+    degraded: AtomicBool,
+    durability: std::sync::Mutex<Durability>,
+    fingerprint: std::sync::Mutex<Option<backend::Fingerprint>>,
+    revision: AtomicU64,
+    changes: watch::Sender<u64>,
+    #[cfg(feature = "testing")]
+    chaos: std::sync::Mutex<Option<testing::Chaos>>,
     _format: PhantomData<F>,
 }
 
@@ -73,26 +120,69 @@ where
     B: Backend,
     F: Format<D>,
 {
-    /// Create a database from its constituents.
+    /// Create a database from its constituents. The database starts out clean, see
+    /// [`Database::is_dirty`](crate::Database::is_dirty).
     pub fn from_parts(data: D, backend: B) -> Self {
         Self {
             data: RwLock::new(data),
+            writes: Mutex::new(()),
             backend: Mutex::new(backend),
+            dirty: AtomicBool::new(false),
+            degrade_on_read_only: AtomicBool::new(false),
+            degraded: AtomicBool::new(false),
+            durability: std::sync::Mutex::new(Durability::FlushedPlusDirSync),
+            fingerprint: std::sync::Mutex::new(None),
+            revision: AtomicU64::new(0),
+            changes: watch::channel(0).0,
+            #[cfg(feature = "testing")]
+            chaos: std::sync::Mutex::new(None),
             _format: PhantomData,
         }
     }
 
+    /// Create a database by loading its data from a backend.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use koit::{Database, backend::Memory, format::Json};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> koit::Result<()> {
+    ///     let db = Database::<Vec<u32>, Memory, Json>::load(Memory::from(b"[1, 2]".to_vec())).await?;
+    ///     assert_eq!(db.read(|data| data.len()).await, 2);
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// If the backend failed to read, or the format failed to decode the data, an error variant
+    /// is returned.
+    pub async fn load(mut backend: B) -> Result<Self> {
+        let (data, fingerprint) = Self::load_parts(&mut backend).await?;
+        let db = Self::from_parts(data, backend);
+        db.set_fingerprint(fingerprint);
+        Ok(db)
+    }
+
     /// Write to the data contained in the database.  This gives exclusive access to the underlying
     /// data structure. The value your closure returns will be passed on as the return value of this
     /// function.
     ///
-    /// This write-locks the data structure.
+    /// This write-locks the data structure, and marks the database dirty. Once the lock is
+    /// released, [subscribers](crate::Database::subscribe) are notified.
     pub async fn write<T, R>(&self, task: T) -> R
     where
         T: FnOnce(&mut D) -> R,
     {
+        let _writes = self.writes.lock().await;
         let mut data = self.data.write().await;
-        task(&mut data)
+        let result = task(&mut data);
+        let revision = self.record_write();
+        drop(data);
+        self.notify(revision);
+        result
     }
 
     /// Same as [`crate::Database::write`], except the task returns a future.
@@ -101,8 +191,120 @@ where
         T: FnOnce(&mut D) -> Fut,
         Fut: Future<Output = R>,
     {
+        let _writes = self.writes.lock().await;
         let mut data = self.data.write().await;
-        task(&mut data).await
+        let result = task(&mut data).await;
+        let revision = self.record_write();
+        drop(data);
+        self.notify(revision);
+        result
+    }
+
+    /// Whether the data was written to since it was last saved or loaded.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty.load(Ordering::Acquire)
+    }
+
+    /// Mark the data as written to, and notify [subscribers](crate::Database::subscribe). This is
+    /// done automatically by the writing methods, but writes through
+    /// [`Database::get_data_lock`](crate::Database::get_data_lock) are not tracked.
+    pub fn mark_dirty(&self) {
+        let revision = self.record_write();
+        self.notify(revision);
+    }
+
+    /// Subscribe to changes of the data. The receiver is notified after every write, with the
+    /// revision of the data: the number of writes since the database was created.
+    ///
+    /// Only writes after subscribing are notified. Without subscribers, notifying is skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use koit::{Database, format::Json, backend::Memory};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let db: Database<u32, Memory, Json> = Database::from_parts(0, Memory::default());
+    ///     db.write(|n| *n += 1).await;
+    ///
+    ///     let mut changes = db.subscribe();
+    ///     assert_eq!(*changes.borrow(), 1);
+    ///
+    ///     db.write(|n| *n += 1).await;
+    ///     changes.changed().await.unwrap();
+    ///     assert_eq!(*changes.borrow_and_update(), 2);
+    ///
+    ///     drop(changes);
+    ///     db.write(|n| *n += 1).await;
+    /// }
+    /// ```
+    ///
+    /// Every subscriber observes every write it waits for, while reading and saving do not
+    /// notify:
+    ///
+    /// ```
+    /// use koit::{Database, format::Json, backend::Memory};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> koit::Result<()> {
+    ///     let db: Database<Vec<u32>, Memory, Json> = Database::from_parts(vec![], Memory::default());
+    ///     let mut first = db.subscribe();
+    ///     let mut second = db.subscribe();
+    ///
+    ///     for revision in 1..=3 {
+    ///         db.write(|data| data.push(revision as u32)).await;
+    ///         for changes in [&mut first, &mut second].iter_mut() {
+    ///             changes.changed().await.unwrap();
+    ///             assert_eq!(*changes.borrow_and_update(), revision);
+    ///         }
+    ///     }
+    ///     assert_eq!(db.revision(), 3);
+    ///
+    ///     db.read(|data| data.len()).await;
+    ///     db.save().await?;
+    ///     assert!(!first.has_changed().unwrap());
+    ///     assert!(!second.has_changed().unwrap());
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn subscribe(&self) -> watch::Receiver<u64> {
+        let changes = self.changes.subscribe();
+        // Catch up on writes that were not notified, without marking them as changed.
+        let revision = self.revision();
+        self.changes.send_if_modified(|current| {
+            *current = revision.max(*current);
+            false
+        });
+        changes
+    }
+
+    /// The revision of the data: the number of writes since the database was created. See
+    /// [`Database::subscribe`](crate::Database::subscribe).
+    pub fn revision(&self) -> u64 {
+        self.revision.load(Ordering::SeqCst)
+    }
+
+    /// Record a write, returning the new revision.
+    fn record_write(&self) -> u64 {
+        self.dirty.store(true, Ordering::Release);
+        self.revision.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    /// Notify subscribers of a revision, if there are any.
+    fn notify(&self, revision: u64) {
+        if self.changes.receiver_count() > 0 {
+            self.changes.send_if_modified(|current| {
+                // Concurrent writers may notify out of order.
+                if *current < revision {
+                    *current = revision;
+                    true
+                } else {
+                    false
+                }
+            });
+        }
     }
 
     /// Read the data contained in the database. Many readers can read in parallel.
@@ -139,7 +341,9 @@ where
     /// Returns a reference to the underlying data lock.
     ///
     /// It is recommended to use the `read` and `write` methods instead of this, to ensure
-    /// locks are only held for as long as needed.
+    /// locks are only held for as long as needed. Writes through this lock do not mark the
+    /// database dirty; use [`Database::mark_dirty`](crate::Database::mark_dirty). They are not
+    /// blocked by [quiescing](crate::Database::quiesce) either.
     ///
     /// # Examples
     ///
@@ -164,7 +368,7 @@ where
 
     /// Returns a mutable reference to the underlying data.
     ///
-    /// This borrows `Database` mutably; no locking takes place.
+    /// This borrows `Database` mutably; no locking takes place. The database is marked dirty.
     ///
     /// # Examples
     ///
@@ -179,45 +383,533 @@ where
     /// futures::executor::block_on(db.read(|n| assert_eq!(*n, 42)));
     /// ```
     pub fn get_data_mut(&mut self) -> &mut D {
+        self.mark_dirty();
         self.data.get_mut()
     }
 
-    /// Flush the data contained in the database to the backend.
+    /// Flush the data contained in the database to the backend, and make it durable to the
+    /// [configured level](crate::Database::set_durability).
     ///
-    /// This read-locks the data structure.
+    /// This read-locks the data structure. On success, the database is no longer dirty, unless
+    /// the database is [degraded](crate::Database::set_degrade_on_read_only), in which case
+    /// nothing is saved and [`Saved::Degraded`](crate::Saved::Degraded) is returned. On failure,
+    /// the database stays dirty, so the save can be retried.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use async_trait::async_trait;
+    /// use koit::{Backend, Database, KoitError, backend::Memory, format::Json};
+    ///
+    /// /// A backend failing to make its first write durable.
+    /// struct FlakyFlush {
+    ///     memory: Memory,
+    ///     failures: usize,
+    /// }
+    ///
+    /// #[async_trait]
+    /// impl Backend for FlakyFlush {
+    ///     type Error = std::io::Error;
+    ///
+    ///     async fn read(&mut self) -> Result<Vec<u8>, Self::Error> {
+    ///         Ok(self.memory.read().await.unwrap())
+    ///     }
+    ///     async fn write(&mut self, data: Vec<u8>) -> Result<(), Self::Error> {
+    ///         Ok(self.memory.write(data).await.unwrap())
+    ///     }
+    ///     async fn flush(&mut self) -> Result<(), Self::Error> {
+    ///         if self.failures > 0 {
+    ///             self.failures -= 1;
+    ///             return Err(std::io::Error::other("disk on fire"));
+    ///         }
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> koit::Result<()> {
+    ///     let backend = FlakyFlush { memory: Memory::new(), failures: 1 };
+    ///     let db = Database::<u32, _, Json>::from_parts(0, backend);
+    ///
+    ///     db.write(|n| *n = 42).await;
+    ///     assert!(matches!(db.save().await, Err(KoitError::BackendWrite(_))));
+    ///     assert!(db.is_dirty());
+    ///
+    ///     db.save().await?;
+    ///     assert!(!db.is_dirty());
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
     ///
     /// # Errors
     ///
     /// - If the data in the database failed to be encoded by the format, an error variant is returned.
     /// - If the bytes failed to be written to the backend, an error variant is returned. This may mean
-    /// the backend is now corrupted.
+    ///   the backend is now corrupted.
+    /// - If the backend failed to make the bytes durable, an error variant is returned.
     ///
     /// # Panics
     ///
     /// Some back-ends (such as [`crate::backend::File`]) might panic on some async runtimes.
-    pub async fn save(&self) -> Result<(), KoitError> {
+    pub async fn save(&self) -> Result<Saved> {
+        self.save_with(self.durability()).await
+    }
+
+    /// Same as [`Database::save`](crate::Database::save), except the data is made durable to
+    /// the given level. See [`Durability`](crate::Durability) for what each level guarantees.
+    ///
+    /// # Examples
+    ///
+    /// Counting the syncs each level costs, with a backend that keeps its data in memory:
+    ///
+    /// ```
+    /// use async_trait::async_trait;
+    /// use koit::{Backend, Database, Durability, backend::ReadOnly, format::Json};
+    ///
+    /// #[derive(Default)]
+    /// struct Counting {
+    ///     data: Vec<u8>,
+    ///     file_syncs: usize,
+    ///     directory_syncs: usize,
+    /// }
+    ///
+    /// #[async_trait]
+    /// impl Backend for Counting {
+    ///     type Error = std::io::Error;
+    ///
+    ///     async fn read(&mut self) -> Result<Vec<u8>, Self::Error> {
+    ///         Ok(self.data.clone())
+    ///     }
+    ///     async fn write(&mut self, data: Vec<u8>) -> Result<(), Self::Error> {
+    ///         self.data = data;
+    ///         Ok(())
+    ///     }
+    ///     async fn flush_with(&mut self, durability: Durability) -> Result<(), Self::Error> {
+    ///         match durability {
+    ///             Durability::Buffered => {}
+    ///             Durability::Flushed => self.file_syncs += 1,
+    ///             Durability::FlushedPlusDirSync => {
+    ///                 self.file_syncs += 1;
+    ///                 self.directory_syncs += 1;
+    ///             }
+    ///         }
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> koit::Result<()> {
+    ///     let db = Database::<u32, Counting, Json>::from_parts(0, Counting::default());
+    ///     let syncs = |db: Database<u32, Counting, Json>| {
+    ///         let (_data, backend) = db.into_parts();
+    ///         (backend.file_syncs, backend.directory_syncs)
+    ///     };
+    ///
+    ///     db.save_with(Durability::Buffered).await?;
+    ///     db.save_without_flush().await?;
+    ///     db.save_with(Durability::Flushed).await?;
+    ///     db.save_with(Durability::FlushedPlusDirSync).await?;
+    ///     // The default level.
+    ///     db.save().await?;
+    ///     assert_eq!(syncs(db), (3, 2));
+    ///
+    ///     let db = Database::<u32, Counting, Json>::from_parts(0, Counting::default());
+    ///     db.set_durability(Durability::Flushed);
+    ///     db.save().await?;
+    ///     db.flush().await?;
+    ///     assert_eq!(syncs(db), (2, 1));
+    ///
+    ///     // Wrappers pass the level on.
+    ///     let mut backend = ReadOnly::new(Counting::default());
+    ///     backend.flush_with(Durability::Flushed).await.unwrap();
+    ///     backend.flush_with(Durability::Buffered).await.unwrap();
+    ///     let backend = backend.into_inner();
+    ///     assert_eq!((backend.file_syncs, backend.directory_syncs), (1, 0));
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// See [`Database::save`](crate::Database::save).
+    pub async fn save_with(&self, durability: Durability) -> Result<Saved> {
         let mut backend = self.backend.lock().await;
-        let data = self.data.read().await;
-        backend
-            .write(F::to_bytes(&data).map_err(|err| KoitError::ToFormat(err.into()))?)
+        if self.is_degraded() {
+            return Ok(Saved::Degraded);
+        }
+        self.save_to(&mut backend, durability).await
+    }
+
+    /// Set the level [`Database::save`](crate::Database::save) makes the data durable to. The
+    /// default is [`Durability::FlushedPlusDirSync`](crate::Durability::FlushedPlusDirSync).
+    pub fn set_durability(&self, durability: Durability) {
+        *self
+            .durability
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner) = durability;
+    }
+
+    /// The level [`Database::save`](crate::Database::save) makes the data durable to, see
+    /// [`Database::set_durability`](crate::Database::set_durability).
+    pub fn durability(&self) -> Durability {
+        *self
+            .durability
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// Same as [`Database::save`](crate::Database::save), except the data is not yet made
+    /// durable, as with [`Durability::Buffered`](crate::Durability::Buffered). This allows
+    /// batching many saves, followed by a single [`Database::flush`](crate::Database::flush).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use koit::{FileDatabase, format::Json};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), koit::KoitError> {
+    ///     let path = std::env::temp_dir().join("koit-batch-example.json");
+    ///     let db = FileDatabase::<Vec<u32>, Json>::load_from_path_or_default(&path).await?;
+    ///
+    ///     for n in 0..100 {
+    ///         db.write(|data| data.push(n)).await;
+    ///         db.save_without_flush().await?;
+    ///     }
+    ///     db.flush().await?;
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - If the data in the database failed to be encoded by the format, an error variant is returned.
+    /// - If the bytes failed to be written to the backend, an error variant is returned. This may mean
+    ///   the backend is now corrupted.
+    pub async fn save_without_flush(&self) -> Result<Saved> {
+        self.save_with(Durability::Buffered).await
+    }
+
+    /// Make the data previously written to the backend durable, to the strongest level. See
+    /// [`Backend::flush`](crate::backend::Backend::flush).
+    ///
+    /// # Errors
+    ///
+    /// If the backend failed to make the bytes durable, an error variant is returned.
+    pub async fn flush(&self) -> Result<()> {
+        let mut backend = self.backend.lock().await;
+        if self.is_degraded() {
+            return Ok(());
+        }
+        self.flush_backend(&mut backend, Durability::FlushedPlusDirSync)
             .await
-            .map_err(|err| KoitError::BackendWrite(err.into()))?;
-        Ok(())
     }
 
-    /// Load data from the backend.
-    async fn load_from_backend(&self) -> Result<D, KoitError> {
+    /// Whether saves that fail because the backend is read-only degrade the database, instead
+    /// of returning an error. Disabled by default.
+    ///
+    /// A save fails because the backend is read-only if the backend reports an I/O error of kind
+    /// [`ReadOnlyFilesystem`](std::io::ErrorKind::ReadOnlyFilesystem) or
+    /// [`PermissionDenied`](std::io::ErrorKind::PermissionDenied). The database then keeps its
+    /// data in memory only: a warning is logged once, the database stays
+    /// [dirty](crate::Database::is_dirty), and saves and flushes do nothing, returning
+    /// [`Saved::Degraded`](crate::Saved::Degraded). Use
+    /// [`Database::try_resume_persistence`](crate::Database::try_resume_persistence) to persist
+    /// again.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+    ///
+    /// use koit::{Backend, Database, Saved, backend::Memory, format::Json};
+    ///
+    /// /// A backend whose write permission can be revoked.
+    /// struct Shared {
+    ///     denied: Arc<AtomicBool>,
+    ///     memory: Memory,
+    /// }
+    ///
+    /// #[async_trait::async_trait]
+    /// impl Backend for Shared {
+    ///     type Error = std::io::Error;
+    ///
+    ///     async fn read(&mut self) -> Result<Vec<u8>, Self::Error> {
+    ///         Ok(self.memory.read().await.unwrap())
+    ///     }
+    ///     async fn write(&mut self, data: Vec<u8>) -> Result<(), Self::Error> {
+    ///         if self.denied.load(Ordering::SeqCst) {
+    ///             return Err(std::io::ErrorKind::PermissionDenied.into());
+    ///         }
+    ///         Ok(self.memory.write(data).await.unwrap())
+    ///     }
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> koit::Result<()> {
+    ///     let denied = Arc::new(AtomicBool::new(false));
+    ///     let shared = Shared { denied: denied.clone(), memory: Memory::default() };
+    ///     let db = Database::<u32, _, Json>::from_parts(0, shared);
+    ///     db.set_degrade_on_read_only(true);
+    ///     assert_eq!(db.save().await?, Saved::Persisted);
+    ///
+    ///     denied.store(true, Ordering::SeqCst);
+    ///     db.write(|n| *n = 1).await;
+    ///     assert_eq!(db.save().await?, Saved::Degraded);
+    ///     assert!(db.is_degraded() && db.is_dirty());
+    ///     db.write(|n| *n = 2).await;
+    ///     assert_eq!(db.save().await?, Saved::Degraded);
+    ///     db.flush().await?;
+    ///     assert_eq!(db.try_resume_persistence().await?, Saved::Degraded);
+    ///
+    ///     denied.store(false, Ordering::SeqCst);
+    ///     assert_eq!(db.try_resume_persistence().await?, Saved::Persisted);
+    ///     assert!(!db.is_degraded() && !db.is_dirty());
+    ///
+    ///     let (_data, mut shared) = db.into_parts();
+    ///     assert_eq!(shared.memory.take(), b"2");
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn set_degrade_on_read_only(&self, enabled: bool) {
+        self.degrade_on_read_only.store(enabled, Ordering::Release);
+    }
+
+    /// Whether the database is degraded, keeping its data in memory only. See
+    /// [`Database::set_degrade_on_read_only`](crate::Database::set_degrade_on_read_only).
+    pub fn is_degraded(&self) -> bool {
+        self.degraded.load(Ordering::Acquire)
+    }
+
+    /// Save the data, even while degraded, leaving degraded mode if that succeeds. Returns
+    /// [`Saved::Degraded`](crate::Saved::Degraded) if the backend is still read-only. See
+    /// [`Database::set_degrade_on_read_only`](crate::Database::set_degrade_on_read_only).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+    ///
+    /// use koit::{Backend, Database, Saved, backend::Memory, format::Json};
+    ///
+    /// /// A backend on a medium that can be mounted read-only.
+    /// struct Mount {
+    ///     read_only: Arc<AtomicBool>,
+    ///     memory: Memory,
+    /// }
+    ///
+    /// #[async_trait::async_trait]
+    /// impl Backend for Mount {
+    ///     type Error = std::io::Error;
+    ///
+    ///     async fn read(&mut self) -> Result<Vec<u8>, Self::Error> {
+    ///         Ok(self.memory.read().await.unwrap())
+    ///     }
+    ///     async fn write(&mut self, data: Vec<u8>) -> Result<(), Self::Error> {
+    ///         if self.read_only.load(Ordering::SeqCst) {
+    ///             return Err(std::io::ErrorKind::ReadOnlyFilesystem.into());
+    ///         }
+    ///         Ok(self.memory.write(data).await.unwrap())
+    ///     }
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> koit::Result<()> {
+    ///     let read_only = Arc::new(AtomicBool::new(true));
+    ///     let mount = Mount { read_only: read_only.clone(), memory: Memory::default() };
+    ///     let db = Database::<Vec<u32>, _, Json>::from_parts(vec![], mount);
+    ///
+    ///     db.write(|data| data.push(1)).await;
+    ///     assert!(db.save().await.is_err());
+    ///
+    ///     db.set_degrade_on_read_only(true);
+    ///     assert_eq!(db.save().await?, Saved::Degraded);
+    ///     assert!(db.is_degraded());
+    ///     db.write(|data| data.push(2)).await;
+    ///     assert_eq!(db.save().await?, Saved::Degraded);
+    ///     assert_eq!(db.try_resume_persistence().await?, Saved::Degraded);
+    ///     assert!(db.is_dirty());
+    ///
+    ///     read_only.store(false, Ordering::SeqCst);
+    ///     assert_eq!(db.try_resume_persistence().await?, Saved::Persisted);
+    ///     assert!(!db.is_degraded() && !db.is_dirty());
+    ///
+    ///     let (_data, mut mount) = db.into_parts();
+    ///     assert_eq!(mount.memory.take(), b"[\n  1,\n  2\n]");
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// If saving failed for another reason than the backend being read-only, an error variant
+    /// is returned, and the database stays degraded.
+    pub async fn try_resume_persistence(&self) -> Result<Saved> {
         let mut backend = self.backend.lock().await;
-        let bytes = backend
-            .read()
+        let saved = self.save_to(&mut backend, self.durability()).await?;
+        if saved == Saved::Persisted && self.degraded.swap(false, Ordering::AcqRel) {
+            tracing::info!("the backend is writable again, the database persists its data");
+        }
+        Ok(saved)
+    }
+
+    /// Save to the locked backend, degrading if it is read-only and the policy allows.
+    async fn save_to(&self, backend: &mut B, durability: Durability) -> Result<Saved> {
+        backend.prepare_write(durability);
+        let result = match self.write_to(backend).await {
+            Ok(()) => self
+                .flush_backend(backend, durability)
+                .await
+                .inspect_err(|_| {
+                    // The written data may not be durable, so the save must be retried.
+                    self.dirty.store(true, Ordering::Release);
+                }),
+            result => result,
+        };
+        match result {
+            Ok(()) => Ok(Saved::Persisted),
+            Err(err) if self.degrade_on_read_only.load(Ordering::Acquire) && is_read_only(&err) => {
+                // The data is kept in memory only.
+                self.dirty.store(true, Ordering::Release);
+                if !self.degraded.swap(true, Ordering::AcqRel) {
+                    tracing::warn!(
+                        "the backend is read-only, the database keeps its data in memory only: {}",
+                        err
+                    );
+                }
+                Ok(Saved::Degraded)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Make the data written to the locked backend durable to the given level.
+    async fn flush_backend(&self, backend: &mut B, durability: Durability) -> Result<()> {
+        #[cfg(feature = "testing")]
+        if durability != Durability::Buffered {
+            if let Some(fault) = self.inject(testing::Point::BeforeFlush) {
+                return Err(fault.into_error());
+            }
+        }
+
+        backend
+            .flush_with(durability)
+            .await
+            .map_err(|err| KoitError::BackendWrite(err.into()))
+    }
+
+    /// Encode the data and write it to the locked backend.
+    async fn write_to(&self, backend: &mut B) -> Result<()> {
+        let data = self.data.read().await;
+        // Writers are excluded while we hold the read lock, so no write can slip in between
+        // clearing the flag and encoding the data.
+        let was_dirty = self.dirty.swap(false, Ordering::AcqRel);
+        let result = self.encode_into(backend, &data).await;
+        if result.is_err() && was_dirty {
+            self.dirty.store(true, Ordering::Release);
+        }
+
+        // Our own save must not count as an external change.
+        let fingerprint = match result {
+            Ok(()) => backend.fingerprint().await.ok().flatten(),
+            Err(_) => None,
+        };
+        self.set_fingerprint(fingerprint);
+        result
+    }
+
+    /// Encode the data into the backend, injecting faults when testing.
+    async fn encode_into(&self, backend: &mut B, data: &D) -> Result<()> {
+        #[cfg(feature = "testing")]
+        {
+            use testing::Point;
+
+            if let Some(fault) = self.inject(Point::BeforeSerialize) {
+                return Err(fault.into_error());
+            }
+            if let Some(fault) = self.inject(Point::BeforeWrite) {
+                F::to_bytes(data).map_err(|err| KoitError::ToFormat(err.into()))?;
+                return Err(fault.into_error());
+            }
+            if let Some(fault) = self.inject(Point::DuringWrite) {
+                let mut bytes = F::to_bytes(data).map_err(|err| KoitError::ToFormat(err.into()))?;
+                bytes.truncate(bytes.len() / 2);
+                backend
+                    .write(bytes)
+                    .await
+                    .map_err(|err| KoitError::BackendWrite(err.into()))?;
+                return Err(fault.into_error());
+            }
+        }
+
+        write_into::<D, F, B>(backend, data).await
+    }
+
+    /// Load data from the locked backend, remembering its fingerprint.
+    async fn load_from_backend(&self, backend: &mut B) -> Result<D> {
+        let (data, fingerprint) = Self::load_parts(backend).await?;
+
+        #[cfg(feature = "testing")]
+        if let Some(fault) = self.inject(testing::Point::DuringParse) {
+            return Err(fault.into_error());
+        }
+
+        self.set_fingerprint(fingerprint);
+        Ok(data)
+    }
+
+    /// Attach a fault injection configuration to the database, returning the previous one. See
+    /// the [testing module](crate::testing).
+    ///
+    /// The configuration only applies to operations on this database, so the initial load by
+    /// constructors such as [`Database::load`](crate::Database::load) is never injected into.
+    #[cfg(feature = "testing")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "testing")))]
+    pub fn set_chaos(&self, chaos: Option<testing::Chaos>) -> Option<testing::Chaos> {
+        std::mem::replace(
+            &mut *self
+                .chaos
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner),
+            chaos,
+        )
+    }
+
+    #[cfg(feature = "testing")]
+    fn inject(&self, point: testing::Point) -> Option<testing::InjectedFault> {
+        self.chaos
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .as_mut()?
+            .inject(point)
+    }
+
+    /// Load data and its fingerprint from a backend.
+    async fn load_parts(backend: &mut B) -> Result<(D, Option<backend::Fingerprint>)> {
+        let fingerprint = backend
+            .fingerprint()
             .await
             .map_err(|err| KoitError::BackendRead(err.into()))?;
-        Ok(F::from_bytes(bytes).map_err(|err| KoitError::FromFormat(err.into()))?)
+        let data = read_from::<D, F, B>(backend).await?;
+        Ok((data, fingerprint))
+    }
+
+    fn set_fingerprint(&self, fingerprint: Option<backend::Fingerprint>) {
+        *self
+            .fingerprint
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner) = fingerprint;
     }
 
     /// Update this database with data from the backend, returning the old data.
     ///
-    /// This will write-lock the internal data structure.
+    /// This will write-lock the internal data structure. On success, the database is no longer
+    /// dirty, and [subscribers](crate::Database::subscribe) are notified.
     ///
     /// # Errors
     ///
@@ -227,9 +919,438 @@ where
     /// # Panics
     ///
     /// Some back-ends (such as [`crate::backend::File`]) might panic on some async runtimes.
-    pub async fn reload(&self) -> Result<D, KoitError> {
-        let new_data = self.load_from_backend().await?;
-        Ok(self.replace(new_data).await)
+    ///
+    /// # Examples
+    ///
+    /// A reload waits for a write in progress, then replaces the data it wrote:
+    ///
+    /// ```
+    /// use koit::{Database, backend::Memory, format::Json};
+    /// use std::sync::Arc;
+    /// use tokio::sync::oneshot;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> koit::Result<()> {
+    ///     let memory = Memory::from(b"42".to_vec());
+    ///     let db = Arc::new(Database::<u32, _, Json>::from_parts(1, memory));
+    ///
+    ///     let (locked, is_locked) = oneshot::channel();
+    ///     let (release, released) = oneshot::channel::<()>();
+    ///     let writer = tokio::spawn({
+    ///         let db = db.clone();
+    ///         async move {
+    ///             db.write_and_then(|n| {
+    ///                 *n = 2;
+    ///                 async move {
+    ///                     locked.send(()).unwrap();
+    ///                     released.await.unwrap();
+    ///                 }
+    ///             })
+    ///             .await
+    ///         }
+    ///     });
+    ///     is_locked.await.unwrap();
+    ///
+    ///     let reload = tokio::spawn({
+    ///         let db = db.clone();
+    ///         async move { db.reload().await }
+    ///     });
+    ///     for _ in 0..10 {
+    ///         tokio::task::yield_now().await;
+    ///     }
+    ///     assert!(!reload.is_finished());
+    ///
+    ///     release.send(()).unwrap();
+    ///     writer.await.unwrap();
+    ///     assert_eq!(reload.await.unwrap()?, 2);
+    ///     assert_eq!(db.read(|n| *n).await, 42);
+    ///     assert!(!db.is_dirty());
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn reload(&self) -> Result<D> {
+        let mut backend = self.backend.lock().await;
+        let new_data = self.load_from_backend(&mut backend).await?;
+        drop(backend);
+        Ok(self.replace_loaded(new_data).await)
+    }
+
+    /// Same as [`Database::reload`](crate::Database::reload), except the data is only reloaded
+    /// if the backend's [fingerprint](crate::backend::Backend::fingerprint) changed since the
+    /// data was last loaded or saved. Returns the old data if the data was reloaded.
+    ///
+    /// Backends without fingerprints are always reloaded.
+    ///
+    /// # Errors
+    ///
+    /// - If the bytes from the backend failed to be decoded by the format, an error variant is
+    ///   returned. The data in the database is left untouched.
+    /// - If the bytes or fingerprint failed to be read by the backend, an error variant is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use koit::{FileDatabase, format::Json};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let path = std::env::temp_dir().join("koit-reload-example.json");
+    ///     std::fs::write(&path, "1")?;
+    ///     let db = FileDatabase::<u32, Json>::load_from_path(&path).await?;
+    ///     assert_eq!(db.reload_if_changed().await?, None);
+    ///
+    ///     // Edited by hand.
+    ///     std::fs::write(&path, "42")?;
+    ///     assert_eq!(db.reload_if_changed().await?, Some(1));
+    ///     assert_eq!(db.reload_if_changed().await?, None);
+    ///     assert_eq!(db.read(|n| *n).await, 42);
+    ///
+    ///     // Our own saves are not external changes.
+    ///     db.write(|n| *n = 420).await;
+    ///     db.save().await?;
+    ///     assert_eq!(db.reload_if_changed().await?, None);
+    ///
+    ///     // Truncated files fail to decode, leaving the data untouched.
+    ///     std::fs::write(&path, "")?;
+    ///     assert!(db.reload_if_changed().await.is_err());
+    ///     assert_eq!(db.read(|n| *n).await, 420);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn reload_if_changed(&self) -> Result<Option<D>> {
+        let mut backend = self.backend.lock().await;
+        let fingerprint = backend
+            .fingerprint()
+            .await
+            .map_err(|err| KoitError::BackendRead(err.into()))?;
+        let known = *self
+            .fingerprint
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        if fingerprint.is_some() && fingerprint == known {
+            return Ok(None);
+        }
+
+        let new_data = self.load_from_backend(&mut backend).await?;
+        drop(backend);
+
+        Ok(Some(self.replace_loaded(new_data).await))
+    }
+
+    /// Replace the data by data freshly loaded from the backend, returning the old data.
+    async fn replace_loaded(&self, new_data: D) -> D {
+        let _writes = self.writes.lock().await;
+        let mut data = self.data.write().await;
+        let old_data = std::mem::replace(&mut *data, new_data);
+        let revision = self.record_write();
+        self.dirty.store(false, Ordering::Release);
+        drop(data);
+        self.notify(revision);
+        old_data
+    }
+
+    /// Quiesce the database, so an external process can safely read the backend, for example to
+    /// back up the database file. This waits for any in-flight save to complete, and
+    /// [flushes](crate::Database::flush) the backend. Until the returned guard is dropped, saves
+    /// wait; depending on `mode`, writes to the in-memory data wait too.
+    ///
+    /// This locks the backend. If writes are blocked, this also waits for in-flight writes to
+    /// complete; the data structure is not locked, so reads continue.
+    ///
+    /// # Errors
+    ///
+    /// If the backend failed to be flushed, an error variant is returned and the database is not
+    /// quiesced.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::{Arc, atomic::{AtomicUsize, Ordering}};
+    /// use std::time::Duration;
+    ///
+    /// use async_trait::async_trait;
+    /// use koit::{Backend, Database, QuiesceMode, format::Json};
+    ///
+    /// #[derive(Default)]
+    /// struct Counting(Arc<AtomicUsize>);
+    ///
+    /// #[async_trait]
+    /// impl Backend for Counting {
+    ///     type Error = std::convert::Infallible;
+    ///
+    ///     async fn read(&mut self) -> Result<Vec<u8>, Self::Error> {
+    ///         Ok(b"0".to_vec())
+    ///     }
+    ///     async fn write(&mut self, _data: Vec<u8>) -> Result<(), Self::Error> {
+    ///         self.0.fetch_add(1, Ordering::SeqCst);
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), koit::KoitError> {
+    ///     let writes = Arc::new(AtomicUsize::new(0));
+    ///     let db: Arc<Database<u32, Counting, Json>> =
+    ///         Arc::new(Database::from_parts(0, Counting(writes.clone())));
+    ///
+    ///     let guard = db.quiesce(QuiesceMode::Saves).await?;
+    ///     assert_eq!(guard.path(), None);
+    ///
+    ///     db.write(|n| *n = 42).await;
+    ///     let save = tokio::spawn({
+    ///         let db = db.clone();
+    ///         async move { db.save().await }
+    ///     });
+    ///     tokio::time::sleep(Duration::from_millis(20)).await;
+    ///     assert_eq!(writes.load(Ordering::SeqCst), 0);
+    ///
+    ///     drop(guard);
+    ///     save.await.unwrap()?;
+    ///     assert_eq!(writes.load(Ordering::SeqCst), 1);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// Blocking writes too leaves reads running, even while a write waits:
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    ///
+    /// use koit::{Database, QuiesceMode, backend::Memory, format::Json};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), koit::KoitError> {
+    ///     let db: Arc<Database<u32, Memory, Json>> =
+    ///         Arc::new(Database::from_parts(0, Memory::default()));
+    ///
+    ///     let guard = db.quiesce(QuiesceMode::SavesAndWrites).await?;
+    ///     let write = tokio::spawn({
+    ///         let db = db.clone();
+    ///         async move { db.write(|n| *n = 42).await }
+    ///     });
+    ///     tokio::time::sleep(Duration::from_millis(20)).await;
+    ///     assert!(!write.is_finished());
+    ///     assert_eq!(db.read(|n| *n).await, 0);
+    ///
+    ///     drop(guard);
+    ///     write.await.unwrap();
+    ///     assert_eq!(db.read(|n| *n).await, 42);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn quiesce(&self, mode: QuiesceMode) -> Result<QuiesceGuard<'_, D, B>> {
+        // In-flight saves hold the backend lock.
+        let mut backend = self.backend.lock().await;
+        self.flush_backend(&mut backend, Durability::FlushedPlusDirSync)
+            .await?;
+        let writes = match mode {
+            QuiesceMode::Saves => None,
+            QuiesceMode::SavesAndWrites => Some(self.writes.lock().await),
+        };
+        Ok(QuiesceGuard::new(backend, writes))
+    }
+
+    /// Run `task` while the database is [quiesced](crate::Database::quiesce), returning its
+    /// output.
+    ///
+    /// # Errors
+    ///
+    /// If the backend failed to be flushed, an error variant is returned and `task` is not run.
+    pub async fn quiesce_and<Fut>(&self, mode: QuiesceMode, task: Fut) -> Result<Fut::Output>
+    where
+        Fut: Future,
+    {
+        let _guard = self.quiesce(mode).await?;
+        Ok(task.await)
+    }
+
+    /// Same as [`Database::quiesce_and`](crate::Database::quiesce_and), except the database is
+    /// released early if `timeout` completes before `task` does. The task still runs to
+    /// completion. Returns the output of the task, and whether the database was released early.
+    ///
+    /// Any future can serve as the timeout, such as `tokio::time::sleep(duration)`.
+    ///
+    /// # Errors
+    ///
+    /// If the backend failed to be flushed, an error variant is returned and `task` is not run.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use koit::{Database, QuiesceMode, format::Json, backend::Memory};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let db: Database<u32, Memory, Json> = Database::from_parts(0, Memory::default());
+    ///     db.write(|n| *n = 42).await;
+    ///
+    ///     let copy = async {
+    ///         tokio::time::sleep(Duration::from_millis(100)).await;
+    ///         // The save below proceeded once the database was released.
+    ///         assert!(!db.is_dirty());
+    ///         "copied"
+    ///     };
+    ///     let quiesced = db.quiesce_and_until(
+    ///         QuiesceMode::Saves,
+    ///         copy,
+    ///         tokio::time::sleep(Duration::from_millis(10)),
+    ///     );
+    ///     let save = async {
+    ///         tokio::time::sleep(Duration::from_millis(20)).await;
+    ///         db.save().await
+    ///     };
+    ///
+    ///     let (quiesced, saved) = tokio::join!(quiesced, save);
+    ///     let (output, timed_out) = quiesced.unwrap();
+    ///     assert_eq!(output, "copied");
+    ///     assert!(timed_out);
+    ///     assert!(saved.is_ok());
+    ///
+    ///     let (_data, mut backend) = db.into_parts();
+    ///     assert_eq!(backend.take(), b"42");
+    /// }
+    /// ```
+    pub async fn quiesce_and_until<Fut, T>(
+        &self,
+        mode: QuiesceMode,
+        task: Fut,
+        timeout: T,
+    ) -> Result<(Fut::Output, bool)>
+    where
+        Fut: Future,
+        T: Future,
+    {
+        let mut guard = Some(self.quiesce(mode).await?);
+        let mut task = std::pin::pin!(task);
+        let mut timeout = std::pin::pin!(timeout);
+
+        let mut timed_out = false;
+        let output = std::future::poll_fn(|cx| {
+            if let std::task::Poll::Ready(output) = task.as_mut().poll(cx) {
+                return std::task::Poll::Ready(output);
+            }
+            if !timed_out && timeout.as_mut().poll(cx).is_ready() {
+                timed_out = true;
+                guard = None;
+            }
+            std::task::Poll::Pending
+        })
+        .await;
+
+        Ok((output, timed_out))
+    }
+
+    /// Start saving this database in the background whenever it is dirty, at most once per
+    /// `interval`. See [`Autosave`](crate::Autosave).
+    ///
+    /// The background task only keeps a weak reference to the database, and stops once the
+    /// database is dropped.
+    ///
+    /// Note: this requires the Tokio runtime.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `interval` is zero.
+    ///
+    /// ```should_panic
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    ///
+    /// use koit::{Database, format::Json, backend::Memory};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let db: Arc<Database<u32, Memory, Json>> = Arc::new(Database::from_parts(0, Memory::default()));
+    ///     let _autosave = db.with_autosave(Duration::ZERO);
+    /// }
+    /// ```
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    ///
+    /// use koit::{Database, format::Json, backend::Memory};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), koit::KoitError> {
+    ///     let db: Arc<Database<u32, Memory, Json>> = Arc::new(Database::from_parts(0, Memory::default()));
+    ///     let autosave = db.with_autosave(Duration::from_millis(10));
+    ///
+    ///     db.write(|n| *n = 42).await;
+    ///     assert!(db.is_dirty());
+    ///     tokio::time::sleep(Duration::from_millis(50)).await;
+    ///     assert!(!db.is_dirty());
+    ///
+    ///     db.write(|n| *n = 43).await;
+    ///     autosave.shutdown().await?;
+    ///
+    ///     let (_data, mut backend) = Arc::try_unwrap(db).unwrap().into_parts();
+    ///     assert_eq!(backend.take(), b"43");
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(feature = "autosave")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "autosave")))]
+    pub fn with_autosave(self: &std::sync::Arc<Self>, interval: std::time::Duration) -> Autosave
+    where
+        D: Send + Sync + 'static,
+        B: Send + 'static,
+        F: Send + Sync + 'static,
+    {
+        autosave::spawn(std::sync::Arc::downgrade(self), interval, None)
+    }
+
+    /// Same as [`Database::with_autosave`](crate::Database::with_autosave), except the
+    /// background saves make the data durable to the given level, instead of the
+    /// [configured level](crate::Database::set_durability).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `interval` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    ///
+    /// use koit::{Database, Durability, backend::Memory, format::Json};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> koit::Result<()> {
+    ///     let db: Arc<Database<u32, Memory, Json>> = Arc::new(Database::from_parts(0, Memory::default()));
+    ///     let autosave = db.with_autosave_durability(Duration::from_millis(10), Durability::Buffered);
+    ///
+    ///     db.write(|n| *n = 42).await;
+    ///     autosave.shutdown().await?;
+    ///     assert!(!db.is_dirty());
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(feature = "autosave")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "autosave")))]
+    pub fn with_autosave_durability(
+        self: &std::sync::Arc<Self>,
+        interval: std::time::Duration,
+        durability: Durability,
+    ) -> Autosave
+    where
+        D: Send + Sync + 'static,
+        B: Send + 'static,
+        F: Send + Sync + 'static,
+    {
+        autosave::spawn(std::sync::Arc::downgrade(self), interval, Some(durability))
     }
 
     /// Consume the database and return its data and backend.
@@ -256,65 +1377,536 @@ where
     /// # Errors
     /// If the file cannot be read, or the [formatter](crate::format::Format) cannot decode the data,
     /// an error variant will be returned.
-    pub async fn load_from_path<P>(path: P) -> Result<Self, KoitError>
+    pub async fn load_from_path<P>(path: P) -> Result<Self>
     where
         P: AsRef<std::path::Path>,
     {
-        let mut backend = backend::File::from_path(path)
+        let backend = backend::File::from_path(path)
             .await
             .map_err(|err| KoitError::BackendCreation(err.into()))?;
-
-        let bytes = backend
-            .read()
-            .await
-            .map_err(|err| KoitError::BackendRead(err.into()))?;
-        let data = F::from_bytes(bytes).map_err(|err| KoitError::FromFormat(err.into()))?;
-
-        Ok(Database {
-            data: RwLock::new(data),
-            backend: Mutex::new(backend),
-            _format: PhantomData,
-        })
+        Self::from_file(backend).await
     }
 
     /// Construct the file-backed database from the given path. If the file does not exist,
     /// the file is created. Then `factory` is called and its return value is used as the initial value.
     /// This data is immediately and saved to file.
-    pub async fn load_from_path_or_else<P, T>(path: P, factory: T) -> Result<Self, KoitError>
+    pub async fn load_from_path_or_else<P, T>(path: P, factory: T) -> Result<Self>
     where
         P: AsRef<std::path::Path>,
         T: FnOnce() -> D,
     {
-        let (mut backend, exists) = backend::File::from_path_or_create(path)
+        let (backend, exists) = backend::File::from_path_or_create(path)
             .await
             .map_err(|e| KoitError::BackendCreation(e.into()))?;
+        Self::from_file_or_else(backend, exists, factory).await
+    }
+
+    /// Same as `load_from_path_or_else`, except it uses [`Default`](`std::default::Default`) instead of a factory.
+    pub async fn load_from_path_or_default<P>(path: P) -> Result<Self>
+    where
+        P: AsRef<std::path::Path>,
+        D: std::default::Default,
+    {
+        Self::load_from_path_or_else(path, || std::default::Default::default()).await
+    }
 
+    /// Same as `load_from_path`, except the file is exclusively locked for as long as the database
+    /// lives. See [`File::from_path_locked`](crate::backend::File::from_path_locked).
+    ///
+    /// # Errors
+    /// If the file cannot be read, or the [formatter](crate::format::Format) cannot decode the data,
+    /// an error variant will be returned. If the file is locked elsewhere and `mode` is
+    /// [`LockMode::NonBlocking`](crate::backend::LockMode::NonBlocking), a
+    /// [`BackendCreation`](crate::KoitError::BackendCreation) error variant will be returned.
+    pub async fn load_from_path_locked<P>(path: P, mode: backend::LockMode) -> Result<Self>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let backend = backend::File::from_path_locked(path, mode)
+            .await
+            .map_err(|err| KoitError::BackendCreation(err.into()))?;
+        Self::from_file(backend).await
+    }
+
+    /// Same as `load_from_path_or_else`, except the file is exclusively locked for as long as the
+    /// database lives. See [`File::from_path_locked`](crate::backend::File::from_path_locked).
+    pub async fn load_from_path_or_else_locked<P, T>(
+        path: P,
+        mode: backend::LockMode,
+        factory: T,
+    ) -> Result<Self>
+    where
+        P: AsRef<std::path::Path>,
+        T: FnOnce() -> D,
+    {
+        let (backend, exists) = backend::File::from_path_or_create_locked(path, mode)
+            .await
+            .map_err(|e| KoitError::BackendCreation(e.into()))?;
+        Self::from_file_or_else(backend, exists, factory).await
+    }
+
+    /// Same as `load_from_path_or_else_locked`, except it uses [`Default`](`std::default::Default`)
+    /// instead of a factory.
+    pub async fn load_from_path_or_default_locked<P>(
+        path: P,
+        mode: backend::LockMode,
+    ) -> Result<Self>
+    where
+        P: AsRef<std::path::Path>,
+        D: std::default::Default,
+    {
+        Self::load_from_path_or_else_locked(path, mode, || std::default::Default::default()).await
+    }
+
+    async fn from_file(mut backend: backend::File) -> Result<Self> {
+        let (data, fingerprint) = Self::load_parts(&mut backend).await?;
+        let db = Database::from_parts(data, backend);
+        db.set_fingerprint(fingerprint);
+        Ok(db)
+    }
+
+    async fn from_file_or_else<T>(
+        mut backend: backend::File,
+        exists: bool,
+        factory: T,
+    ) -> Result<Self>
+    where
+        T: FnOnce() -> D,
+    {
         let data = if exists {
-            let bytes = backend
-                .read()
-                .await
-                .map_err(|err| KoitError::BackendRead(err.into()))?;
-            F::from_bytes(bytes).map_err(|err| KoitError::FromFormat(err.into()))?
+            Self::load_parts(&mut backend).await?.0
         } else {
             factory()
         };
 
-        let db = Database {
-            data: RwLock::new(data),
-            backend: Mutex::new(backend),
-            _format: PhantomData,
-        };
-
+        // Saving records the fingerprint.
+        let db = Database::from_parts(data, backend);
         db.save().await?;
         Ok(db)
     }
+}
 
-    /// Same as `load_from_path_or_else`, except it uses [`Default`](`std::default::Default`) instead of a factory.
-    pub async fn load_from_path_or_default<P>(path: P) -> Result<Self, KoitError>
+/// A database backed by a [`FilePath`](crate::backend::FilePath), replacing the file on every
+/// save and optionally keeping backups.
+#[cfg(feature = "file-backend")]
+#[cfg_attr(docsrs, doc(cfg(feature = "file-backend")))]
+pub type FilePathDatabase<D, F> = Database<D, backend::FilePath, F>;
+
+#[cfg(feature = "file-backend")]
+impl<D, F> FilePathDatabase<D, F>
+where
+    F: Format<D>,
+{
+    /// Construct the database from the file at the given path, keeping `keep` backups (see
+    /// [`FilePath::with_backups`](crate::backend::FilePath::with_backups)). If the file fails
+    /// to decode, the backups are tried newest-first, and the first one that decodes is loaded.
+    ///
+    /// Returns the database, and the path of the backup it was recovered from, if any. A
+    /// recovered database starts out [dirty](crate::Database::is_dirty), as the file no longer
+    /// matches its data; the next save replaces the file, rotating the undecodable file into the
+    /// backups.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be read, an error variant is returned. If it fails to decode, and no
+    /// backup decodes either, the decoding error of the file is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use koit::{FilePathDatabase, backend::FilePath, format::Json};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> koit::Result<()> {
+    ///     let dir = std::env::temp_dir().join("koit-recover-example");
+    ///     let _ = std::fs::remove_dir_all(&dir);
+    ///     std::fs::create_dir_all(&dir).unwrap();
+    ///     let path = dir.join("db.json");
+    ///
+    ///     let db = FilePathDatabase::<Vec<u32>, Json>::from_parts(vec![], FilePath::with_backups(&path, 3));
+    ///     for n in 1..=2 {
+    ///         db.write(|data| data.push(n)).await;
+    ///         db.save().await?;
+    ///     }
+    ///     drop(db);
+    ///
+    ///     // A file that loads needs no recovery.
+    ///     let (db, recovered) = FilePathDatabase::<Vec<u32>, Json>::load_from_path_or_recover(&path, 3).await?;
+    ///     assert_eq!(recovered, None);
+    ///     assert_eq!(db.read(|data| data.clone()).await, vec![1, 2]);
+    ///     drop(db);
+    ///
+    ///     // Simulate a torn write.
+    ///     std::fs::write(&path, b"[1, 2, 3").unwrap();
+    ///
+    ///     let (db, recovered) = FilePathDatabase::<Vec<u32>, Json>::load_from_path_or_recover(&path, 3).await?;
+    ///     assert_eq!(recovered, Some(dir.join("db.json.1")));
+    ///     assert_eq!(db.read(|data| data.clone()).await, vec![1]);
+    ///     assert!(db.is_dirty());
+    ///
+    ///     // With every copy corrupt, the decoding error is returned.
+    ///     std::fs::write(dir.join("db.json.1"), b"cat").unwrap();
+    ///     let result = FilePathDatabase::<Vec<u32>, Json>::load_from_path_or_recover(&path, 3).await;
+    ///     assert!(matches!(result, Err(koit::Error::FromFormat(_))));
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn load_from_path_or_recover<P>(
+        path: P,
+        keep: usize,
+    ) -> Result<(Self, Option<std::path::PathBuf>)>
     where
         P: AsRef<std::path::Path>,
-        D: std::default::Default,
     {
-        Self::load_from_path_or_else(path, || std::default::Default::default()).await
+        let (db, recovered) = Self::load_from_path_or_repair(path, keep, &[]).await?;
+        let backup = match recovered {
+            Some(repair::Recovered::Backup(backup)) => Some(backup),
+            _ => None,
+        };
+        Ok((db, backup))
+    }
+
+    /// Same as
+    /// [`FilePathDatabase::load_from_path_or_recover`](crate::FilePathDatabase::load_from_path_or_recover),
+    /// except a file that fails to decode is first [repaired](crate::repair::repair) with the
+    /// given passes. Only if the repair fails are the backups tried.
+    ///
+    /// Before a repaired file is loaded, the original is copied aside to the first free path of
+    /// `<path>.corrupt`, `<path>.corrupt.1`, `<path>.corrupt.2` and so on, so the repair can
+    /// always be undone. A repaired database starts out [dirty](crate::Database::is_dirty); the
+    /// next save writes the repaired data to the file.
+    ///
+    /// Returns the database, and how it was recovered, if it needed recovery.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be read, or the original cannot be copied aside, an error variant is
+    /// returned. If the file fails to decode, cannot be repaired, and no backup decodes, the
+    /// decoding error of the file is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use koit::{FilePathDatabase, backend::FilePath, format::Json, repair::{Pass, Recovered}};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> koit::Result<()> {
+    ///     let dir = std::env::temp_dir().join("koit-repair-example");
+    ///     let _ = std::fs::remove_dir_all(&dir);
+    ///     std::fs::create_dir_all(&dir).unwrap();
+    ///     let path = dir.join("db.json");
+    ///     type Db = FilePathDatabase<Vec<Vec<u32>>, Json>;
+    ///
+    ///     let db = Db::from_parts(vec![], FilePath::with_backups(&path, 1));
+    ///     for n in 1..=2 {
+    ///         db.write(|data| data.push(vec![n])).await;
+    ///         db.save().await?;
+    ///     }
+    ///     drop(db);
+    ///
+    ///     // Lose the closing bracket.
+    ///     std::fs::write(&path, b"[[1], [2]").unwrap();
+    ///
+    ///     let (db, recovered) = Db::load_from_path_or_repair(&path, 1, &Pass::ALL).await?;
+    ///     assert_eq!(
+    ///         recovered,
+    ///         Some(Recovered::Repaired {
+    ///             original: dir.join("db.json.corrupt"),
+    ///             applied: vec![Pass::BalanceBrackets],
+    ///         })
+    ///     );
+    ///     assert_eq!(db.read(|data| data.clone()).await, vec![vec![1], vec![2]]);
+    ///     assert!(db.is_dirty());
+    ///     assert_eq!(std::fs::read(dir.join("db.json.corrupt")).unwrap(), b"[[1], [2]");
+    ///     drop(db);
+    ///
+    ///     // A file torn mid-value is not repaired, and the backup is loaded instead.
+    ///     std::fs::write(&path, b"[[1], [2").unwrap();
+    ///     let (db, recovered) = Db::load_from_path_or_repair(&path, 1, &Pass::ALL).await?;
+    ///     assert_eq!(recovered, Some(Recovered::Backup(dir.join("db.json.1"))));
+    ///     assert_eq!(db.read(|data| data.clone()).await, vec![vec![1]]);
+    ///     // Nothing was set aside.
+    ///     assert!(!dir.join("db.json.corrupt.1").exists());
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn load_from_path_or_repair<P>(
+        path: P,
+        keep: usize,
+        passes: &[repair::Pass],
+    ) -> Result<(Self, Option<repair::Recovered>)>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let mut backend = backend::FilePath::with_backups(path, keep);
+        let err = match Self::load_parts(&mut backend).await {
+            Ok((data, fingerprint)) => {
+                let db = Database::from_parts(data, backend);
+                db.set_fingerprint(fingerprint);
+                return Ok((db, None));
+            }
+            Err(err @ KoitError::FromFormat(_)) => err,
+            Err(err) => return Err(err),
+        };
+
+        if !passes.is_empty() {
+            let path = backend.path().expect("file paths have a path").to_owned();
+            let bytes = tokio::fs::read(&path)
+                .await
+                .map_err(|err| KoitError::BackendRead(err.into()))?;
+            if let Ok(repaired) = repair::repair::<D, F>(&bytes, passes) {
+                let original = set_aside(&path, &bytes)
+                    .await
+                    .map_err(|err| KoitError::BackendWrite(err.into()))?;
+                let applied = repaired.applied().to_vec();
+                let data = F::from_bytes(repaired.into_bytes())
+                    .map_err(|err| KoitError::FromFormat(err.into()))?;
+
+                let fingerprint = backend.fingerprint().await.ok().flatten();
+                let db = Database::from_parts(data, backend);
+                db.set_fingerprint(fingerprint);
+                db.mark_dirty();
+                return Ok((db, Some(repair::Recovered::Repaired { original, applied })));
+            }
+        }
+
+        for n in 1..=keep {
+            let backup = backend.backup_path(n);
+            let data = match tokio::fs::read(&backup).await {
+                Ok(bytes) => match F::from_bytes(bytes) {
+                    Ok(data) => data,
+                    Err(_) => continue,
+                },
+                Err(_) => continue,
+            };
+
+            // Only a change of the file itself should count as an external change.
+            let fingerprint = backend.fingerprint().await.ok().flatten();
+            let db = Database::from_parts(data, backend);
+            db.set_fingerprint(fingerprint);
+            db.mark_dirty();
+            return Ok((db, Some(repair::Recovered::Backup(backup))));
+        }
+        Err(err)
+    }
+}
+
+/// Copy the bytes of a corrupt file to the first free path of `<path>.corrupt`,
+/// `<path>.corrupt.1` and so on, returning that path.
+#[cfg(feature = "file-backend")]
+async fn set_aside(path: &std::path::Path, bytes: &[u8]) -> std::io::Result<std::path::PathBuf> {
+    use tokio::io::AsyncWriteExt;
+
+    for n in 0.. {
+        let mut aside = path.as_os_str().to_owned();
+        aside.push(".corrupt");
+        if n > 0 {
+            aside.push(format!(".{}", n));
+        }
+        let aside = std::path::PathBuf::from(aside);
+
+        let mut file = match tokio::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&aside)
+            .await
+        {
+            Ok(file) => file,
+            Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err),
+        };
+        file.write_all(bytes).await?;
+        file.sync_all().await?;
+        return Ok(aside);
+    }
+    unreachable!("ran out of paths to set a corrupt file aside at")
+}
+
+#[cfg(all(feature = "file-backend", feature = "serde"))]
+impl<E, F> FileDatabase<E::Latest, versioned::Upgrade<E, F>>
+where
+    E: versioned::Latest,
+    F: Format<E> + for<'a> Format<versioned::AsLatest<'a, E>, Error = <F as Format<E>>::Error>,
+{
+    /// Construct the file-backed database from the given path, upgrading the enum-versioned data
+    /// in the file to its newest version. See the [versioned module](crate::versioned).
+    ///
+    /// Returns the database and whether the data was upgraded. If the data was upgraded and
+    /// `resave` is set, the upgraded data is immediately saved to file. Otherwise, an upgraded
+    /// database starts out [dirty](crate::Database::is_dirty), as the file no longer matches its
+    /// data.
+    ///
+    /// # Errors
+    /// If the file cannot be read, or the [formatter](crate::format::Format) cannot decode the data,
+    /// an error variant will be returned. If the upgraded data cannot be saved, an error variant
+    /// will be returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use koit::{FileDatabase, format::Json, versioned::{Latest, Upgrade}};
+    /// use serde::{Deserialize, Serialize, Serializer};
+    ///
+    /// #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    /// enum Data {
+    ///     V1(u8),
+    ///     V2(u16),
+    ///     V3(u32),
+    /// }
+    ///
+    /// impl Latest for Data {
+    ///     type Latest = u32;
+    ///
+    ///     fn upgrade(self) -> u32 {
+    ///         match self {
+    ///             Data::V1(n) => Data::V2(u16::from(n) * 10).upgrade(),
+    ///             Data::V2(n) => u32::from(n) * 10,
+    ///             Data::V3(n) => n,
+    ///         }
+    ///     }
+    ///
+    ///     fn is_latest(&self) -> bool {
+    ///         matches!(self, Data::V3(_))
+    ///     }
+    ///
+    ///     fn serialize_latest<S: Serializer>(latest: &u32, serializer: S) -> Result<S::Ok, S::Error> {
+    ///         serializer.serialize_newtype_variant("Data", 2, "V3", latest)
+    ///     }
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let path = std::env::temp_dir().join("koit-versioned-example.json");
+    ///     let cases = [
+    ///         (Data::V1(4), 400, true),
+    ///         (Data::V2(40), 400, true),
+    ///         (Data::V3(400), 400, false),
+    ///     ];
+    ///
+    ///     for (stored, expected, upgrades) in cases {
+    ///         for resave in [false, true] {
+    ///             std::fs::write(&path, serde_json::to_vec(&stored)?)?;
+    ///
+    ///             let (db, upgraded) =
+    ///                 FileDatabase::<u32, Upgrade<Data, Json>>::load_from_path_versioned(&path, resave)
+    ///                     .await?;
+    ///             assert_eq!(upgraded, upgrades);
+    ///             assert_eq!(db.read(|n| *n).await, expected);
+    ///             assert_eq!(db.is_dirty(), upgrades && !resave);
+    ///
+    ///             let on_disk: Data = serde_json::from_slice(&std::fs::read(&path)?)?;
+    ///             if upgrades && resave {
+    ///                 assert_eq!(on_disk, Data::V3(expected));
+    ///             } else {
+    ///                 assert_eq!(on_disk, stored);
+    ///             }
+    ///         }
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn load_from_path_versioned<P>(path: P, resave: bool) -> Result<(Self, bool)>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let mut backend = backend::File::from_path(path)
+            .await
+            .map_err(|err| KoitError::BackendCreation(err.into()))?;
+
+        // Fingerprinted first, so a write racing the read is noticed by a later reload.
+        let fingerprint = backend
+            .fingerprint()
+            .await
+            .map_err(|err| KoitError::BackendRead(err.into()))?;
+        let data = read_from::<E, F, backend::File>(&mut backend).await?;
+        let upgraded = !data.is_latest();
+
+        let db = Database::from_parts(data.upgrade(), backend);
+        db.set_fingerprint(fingerprint);
+        if upgraded {
+            if resave {
+                db.save().await?;
+            } else {
+                db.mark_dirty();
+            }
+        }
+        Ok((db, upgraded))
+    }
+}
+
+/// Encode data into a backend, streaming if the backend supports it.
+async fn write_into<T, F, B>(backend: &mut B, data: &T) -> Result<()>
+where
+    F: Format<T>,
+    B: Backend,
+    B::Error: std::error::Error + Send + Sync + 'static,
+{
+    let streamed = backend::blocking(|| match backend.writer() {
+        Ok(Some(mut writer)) => {
+            F::to_writer(data, &mut writer)?;
+            writer
+                .flush()
+                .map_err(|err| KoitError::BackendWrite(err.into()))?;
+            Ok(true)
+        }
+        Ok(None) => Ok(false),
+        Err(err) => Err(KoitError::BackendWrite(err.into())),
+    })?;
+    if streamed {
+        return backend
+            .finish_write()
+            .await
+            .map_err(|err| KoitError::BackendWrite(err.into()));
+    }
+
+    let bytes = F::to_bytes(data).map_err(|err| KoitError::ToFormat(err.into()))?;
+    backend
+        .write(bytes)
+        .await
+        .map_err(|err| KoitError::BackendWrite(err.into()))
+}
+
+/// Decode data from a backend, streaming if the backend supports it.
+async fn read_from<T, F, B>(backend: &mut B) -> Result<T>
+where
+    F: Format<T>,
+    B: Backend,
+    B::Error: std::error::Error + Send + Sync + 'static,
+{
+    let streamed = backend::blocking(|| match backend.reader() {
+        Ok(Some(mut reader)) => Some(F::from_reader(&mut reader)),
+        Ok(None) => None,
+        Err(err) => Some(Err(KoitError::BackendRead(err.into()))),
+    });
+    if let Some(result) = streamed {
+        return result;
+    }
+
+    let bytes = backend
+        .read()
+        .await
+        .map_err(|err| KoitError::BackendRead(err.into()))?;
+    F::from_bytes(bytes).map_err(|err| KoitError::FromFormat(err.into()))
+}
+
+/// Whether an error was caused by the backend being read-only.
+fn is_read_only(err: &KoitError) -> bool {
+    let source = match err {
+        KoitError::BackendWrite(source) => source,
+        _ => return false,
+    };
+    let mut current: Option<&(dyn std::error::Error + 'static)> = Some(source.as_ref());
+    while let Some(err) = current {
+        if let Some(err) = err.downcast_ref::<std::io::Error>() {
+            return matches!(
+                err.kind(),
+                std::io::ErrorKind::ReadOnlyFilesystem | std::io::ErrorKind::PermissionDenied
+            );
+        }
+        current = err.source();
     }
+    false
 }
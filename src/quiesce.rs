@@ -0,0 +1,50 @@
+use std::marker::PhantomData;
+use std::path::Path;
+
+use tokio::sync::MutexGuard;
+
+use crate::Backend;
+
+/// What a [quiesced](crate::Database::quiesce) database blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuiesceMode {
+    /// Block saves. Writes to the in-memory data continue.
+    Saves,
+    /// Block saves and writes to the in-memory data. Reads continue, even while writes are
+    /// waiting.
+    SavesAndWrites,
+}
+
+/// A guard keeping a database quiesced, created by
+/// [`Database::quiesce`](crate::Database::quiesce). While the guard lives, the backend is not
+/// written to. Dropping the guard releases the database; queued saves then proceed.
+#[derive(Debug)]
+pub struct QuiesceGuard<'a, D, B> {
+    backend: MutexGuard<'a, B>,
+    _writes: Option<MutexGuard<'a, ()>>,
+    _data: PhantomData<&'a D>,
+}
+
+impl<'a, D, B> QuiesceGuard<'a, D, B>
+where
+    B: Backend,
+{
+    pub(crate) fn new(backend: MutexGuard<'a, B>, writes: Option<MutexGuard<'a, ()>>) -> Self {
+        Self {
+            backend,
+            _writes: writes,
+            _data: PhantomData,
+        }
+    }
+
+    /// The quiesced backend.
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    /// The path of the file backing the quiesced backend, if any. See
+    /// [`Backend::path`](crate::backend::Backend::path).
+    pub fn path(&self) -> Option<&Path> {
+        self.backend.path()
+    }
+}
@@ -0,0 +1,424 @@
+//! Conservative repairs of corrupt payloads.
+//!
+//! Three corruptions come up in practice: a truncated JSON document missing its closing
+//! brackets, a payload written twice back to back, and invalid UTF-8 left behind by an editor.
+//! [`detect`](crate::repair::detect) names the issues of a payload, and
+//! [`repair`](crate::repair::repair) runs [passes](crate::repair::Pass) undoing them on the raw
+//! bytes. Each pass only fires on the exact shape of corruption it undoes, and a repair is only
+//! accepted if the result decodes in the target format. Otherwise, the repair refuses with
+//! [`Unrepairable`](crate::repair::Unrepairable) rather than guess.
+//!
+//! The target format is given as a type parameter, along with the type of the data. The passes
+//! are aimed at text formats such as [JSON](crate::format::Json); binary formats are lenient
+//! enough that a mangled payload may still decode.
+//!
+//! To repair a file while loading it, see
+//! [`FilePathDatabase::load_from_path_or_repair`](crate::FilePathDatabase::load_from_path_or_repair).
+//!
+//! # Examples
+//!
+//! ```
+//! use koit::{format::Json, repair::{self, Issue, Pass}};
+//!
+//! // A payload written twice, the second time torn.
+//! let bytes = b"{\"cats\": 2}\n{\"ca";
+//! type Data = std::collections::BTreeMap<String, u32>;
+//!
+//! assert_eq!(repair::detect::<Data, Json>(bytes), vec![Issue::TrailingData { offset: 11 }]);
+//! // The torn second document is not complete, so it is not shed.
+//! assert!(repair::repair::<Data, Json>(bytes, &Pass::ALL).is_err());
+//!
+//! // A payload written twice in full.
+//! let bytes = b"{\"cats\": 2}\n{\"cats\": 2}\n";
+//! let repaired = repair::repair::<Data, Json>(bytes, &Pass::ALL).unwrap();
+//! assert_eq!(repaired.applied(), [Pass::FirstDocument]);
+//! assert_eq!(repaired.bytes(), b"{\"cats\": 2}");
+//! ```
+
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::Format;
+
+/// An issue [detected](crate::repair::detect) in a payload that fails to decode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Issue {
+    /// The payload is not valid UTF-8, first at the given byte offset.
+    InvalidUtf8 {
+        /// The offset of the first invalid byte.
+        offset: usize,
+    },
+    /// A complete JSON object or array ends at the given byte offset, but more data follows.
+    TrailingData {
+        /// The offset just past the end of the first document.
+        offset: usize,
+    },
+    /// The payload ends with JSON brackets left open.
+    Unclosed {
+        /// The number of brackets left open.
+        brackets: usize,
+    },
+    /// The payload fails to decode for a reason not listed above.
+    Unrecognized,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::InvalidUtf8 { offset } => write!(f, "invalid UTF-8 at byte {}", offset),
+            Issue::TrailingData { offset } => write!(f, "trailing data after byte {}", offset),
+            Issue::Unclosed { brackets } => write!(f, "{} unclosed brackets", brackets),
+            Issue::Unrecognized => write!(f, "an unrecognized corruption"),
+        }
+    }
+}
+
+/// A repair pass. Passes run in the order given to [`repair`](crate::repair::repair), each on
+/// the output of the previous one, and each either fires, changing the bytes as documented, or
+/// leaves them untouched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Pass {
+    /// Remove every invalid UTF-8 sequence, keeping all valid characters around it. Fires if the
+    /// payload is not valid UTF-8.
+    ///
+    /// ```
+    /// use koit::{format::Json, repair::{self, Pass}};
+    ///
+    /// let repaired = repair::repair::<String, Json>(b"\"caf\xc3\"", &[Pass::StripInvalidUtf8]).unwrap();
+    /// assert_eq!(repaired.bytes(), b"\"caf\"");
+    ///
+    /// // Stripping must leave a payload that decodes.
+    /// assert!(repair::repair::<Vec<u32>, Json>(b"[1, \xff]", &[Pass::StripInvalidUtf8]).is_err());
+    /// ```
+    StripInvalidUtf8,
+    /// Keep the first complete JSON document, dropping what follows it. Fires if the payload
+    /// starts with a JSON object or array, and is followed only by further complete objects or
+    /// arrays, separated by whitespace. Trailing data of any other shape is left alone.
+    ///
+    /// ```
+    /// use koit::{format::Json, repair::{self, Pass}};
+    ///
+    /// let bytes = br#"["}{", "]["] ["}{", "]["]"#;
+    /// let repaired = repair::repair::<Vec<String>, Json>(bytes, &[Pass::FirstDocument]).unwrap();
+    /// assert_eq!(repaired.bytes(), br#"["}{", "]["]"#);
+    ///
+    /// // Trailing garbage is not a second document.
+    /// assert!(repair::repair::<Vec<u32>, Json>(b"[1] cat", &[Pass::FirstDocument]).is_err());
+    /// ```
+    FirstDocument,
+    /// Append the closing brackets a truncated JSON document misses. Fires if the payload
+    /// starts with a JSON object or array, its brackets nest properly, and it ends outside of a
+    /// string directly after a closing bracket. Truncated values are thus never completed: a
+    /// payload torn inside a string, a number or after a separator is left alone. Whatever
+    /// followed the truncation point is lost.
+    ///
+    /// ```
+    /// use koit::{format::Json, repair::{self, Pass}};
+    ///
+    /// let bytes = b"{\"cats\": [1, 2], \"yaks\": [3]";
+    /// let repaired = repair::repair::<serde_json::Value, Json>(bytes, &[Pass::BalanceBrackets]).unwrap();
+    /// assert_eq!(repaired.bytes(), b"{\"cats\": [1, 2], \"yaks\": [3]}");
+    ///
+    /// for torn in [&b"[[1], [2"[..], b"{\"name\": \"Al", b"[[1],", b"{\"a\": [1}"] {
+    ///     assert!(repair::repair::<serde_json::Value, Json>(torn, &[Pass::BalanceBrackets]).is_err());
+    /// }
+    /// ```
+    BalanceBrackets,
+}
+
+impl Pass {
+    /// All passes, in the order they are best run in.
+    pub const ALL: [Pass; 3] = [
+        Pass::StripInvalidUtf8,
+        Pass::FirstDocument,
+        Pass::BalanceBrackets,
+    ];
+
+    /// Run the pass, returning the changed bytes if it fires.
+    fn apply(self, bytes: &[u8]) -> Option<Vec<u8>> {
+        match self {
+            Pass::StripInvalidUtf8 => strip_invalid_utf8(bytes),
+            Pass::FirstDocument => first_document(bytes),
+            Pass::BalanceBrackets => balance_brackets(bytes),
+        }
+    }
+}
+
+/// A payload that [`repair`](crate::repair::repair) made decode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepairedPayload {
+    bytes: Vec<u8>,
+    applied: Vec<Pass>,
+}
+
+impl RepairedPayload {
+    /// The repaired bytes.
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Consume the payload and return the repaired bytes.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    /// The passes that fired, in order. Empty if the payload decoded as it was.
+    pub fn applied(&self) -> &[Pass] {
+        &self.applied
+    }
+}
+
+/// The error returned if a payload could not be repaired.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("the data could not be repaired ({})", DisplayIssues(.issues))]
+pub struct Unrepairable {
+    issues: Vec<Issue>,
+}
+
+impl Unrepairable {
+    /// The issues [detected](crate::repair::detect) in the original payload.
+    pub fn issues(&self) -> &[Issue] {
+        &self.issues
+    }
+}
+
+struct DisplayIssues<'a>(&'a [Issue]);
+
+impl fmt::Display for DisplayIssues<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (n, issue) in self.0.iter().enumerate() {
+            if n > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+/// How a [`FilePathDatabase`](crate::FilePathDatabase) was recovered from a file that failed to
+/// decode, see
+/// [`FilePathDatabase::load_from_path_or_repair`](crate::FilePathDatabase::load_from_path_or_repair).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Recovered {
+    /// The file was repaired.
+    Repaired {
+        /// The path the original file was preserved at.
+        original: PathBuf,
+        /// The passes that fired.
+        applied: Vec<Pass>,
+    },
+    /// The file could not be repaired, and the backup at the given path was loaded instead.
+    Backup(PathBuf),
+}
+
+/// Detect the issues of a payload in the given format. Returns no issues if the payload decodes.
+///
+/// # Examples
+///
+/// ```
+/// use koit::{format::Json, repair::{self, Issue}};
+///
+/// assert_eq!(repair::detect::<Vec<u32>, Json>(b"[1, 2]"), vec![]);
+/// assert_eq!(repair::detect::<Vec<u32>, Json>(b"[1, 2"), vec![Issue::Unclosed { brackets: 1 }]);
+/// assert_eq!(
+///     repair::detect::<Vec<u32>, Json>(b"[\xff] [1]"),
+///     vec![Issue::InvalidUtf8 { offset: 1 }, Issue::TrailingData { offset: 3 }]
+/// );
+/// assert_eq!(repair::detect::<Vec<u32>, Json>(b"cat"), vec![Issue::Unrecognized]);
+/// ```
+pub fn detect<T, F>(bytes: &[u8]) -> Vec<Issue>
+where
+    F: Format<T>,
+{
+    if F::from_bytes(bytes.to_vec()).is_ok() {
+        return Vec::new();
+    }
+
+    let mut issues = Vec::new();
+    if let Err(err) = std::str::from_utf8(bytes) {
+        issues.push(Issue::InvalidUtf8 {
+            offset: err.valid_up_to(),
+        });
+    }
+    match scan(bytes) {
+        Scan::Complete(end) if !is_blank(&bytes[end..]) => {
+            issues.push(Issue::TrailingData { offset: end })
+        }
+        Scan::Open { closers, .. } => issues.push(Issue::Unclosed {
+            brackets: closers.len(),
+        }),
+        _ => {}
+    }
+    if issues.is_empty() {
+        issues.push(Issue::Unrecognized);
+    }
+    issues
+}
+
+/// Repair a payload in the given format, running the given passes in order.
+///
+/// A payload that decodes already is returned as is. Otherwise, the passes run, and the repair
+/// succeeds if the result decodes.
+///
+/// # Errors
+///
+/// If the repaired payload does not decode, [`Unrepairable`](crate::repair::Unrepairable) is
+/// returned with the issues detected in the original payload.
+///
+/// # Examples
+///
+/// ```
+/// use koit::{format::Json, repair::{self, Issue, Pass}};
+///
+/// // A payload with invalid UTF-8, written twice.
+/// let bytes = b"[\"na\xefve\"] [\"na\xefve\"]";
+/// let repaired = repair::repair::<Vec<String>, Json>(bytes, &Pass::ALL).unwrap();
+/// assert_eq!(repaired.applied(), [Pass::StripInvalidUtf8, Pass::FirstDocument]);
+/// assert_eq!(repaired.bytes(), b"[\"nave\"]");
+///
+/// // Passes do not guess: the second copy is torn.
+/// let bytes = b"[\"na\xefve\"] [\"na";
+/// let err = repair::repair::<Vec<String>, Json>(bytes, &Pass::ALL).unwrap_err();
+/// assert_eq!(err.issues(), [Issue::InvalidUtf8 { offset: 4 }, Issue::TrailingData { offset: 9 }]);
+///
+/// // Only the given passes run.
+/// assert!(repair::repair::<Vec<u32>, Json>(b"[1] [1]", &[Pass::BalanceBrackets]).is_err());
+/// assert!(repair::repair::<Vec<u32>, Json>(b"[1]", &[]).unwrap().applied().is_empty());
+/// ```
+pub fn repair<T, F>(bytes: &[u8], passes: &[Pass]) -> Result<RepairedPayload, Unrepairable>
+where
+    F: Format<T>,
+{
+    let mut repaired = bytes.to_vec();
+    let mut applied = Vec::new();
+    if F::from_bytes(repaired.clone()).is_err() {
+        for &pass in passes {
+            if let Some(bytes) = pass.apply(&repaired) {
+                repaired = bytes;
+                applied.push(pass);
+            }
+        }
+        if applied.is_empty() || F::from_bytes(repaired.clone()).is_err() {
+            return Err(Unrepairable {
+                issues: detect::<T, F>(bytes),
+            });
+        }
+    }
+
+    Ok(RepairedPayload {
+        bytes: repaired,
+        applied,
+    })
+}
+
+/// The JSON structure at the start of a payload.
+enum Scan {
+    /// A complete object or array, ending at the given offset.
+    Complete(usize),
+    /// An object or array cut off, with the brackets closing it, innermost last.
+    Open { closers: Vec<u8>, in_string: bool },
+    /// Anything else, including mismatched brackets.
+    Other,
+}
+
+fn scan(bytes: &[u8]) -> Scan {
+    let start = match bytes.iter().position(|byte| !byte.is_ascii_whitespace()) {
+        Some(start) if bytes[start] == b'{' || bytes[start] == b'[' => start,
+        _ => return Scan::Other,
+    };
+
+    let mut closers = Vec::new();
+    let mut in_string = false;
+    let mut escaped = false;
+    for (offset, &byte) in bytes.iter().enumerate().skip(start) {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match byte {
+            b'"' => in_string = true,
+            b'{' => closers.push(b'}'),
+            b'[' => closers.push(b']'),
+            b'}' | b']' => {
+                if closers.pop() != Some(byte) {
+                    return Scan::Other;
+                }
+                if closers.is_empty() {
+                    return Scan::Complete(offset + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    Scan::Open { closers, in_string }
+}
+
+fn is_blank(bytes: &[u8]) -> bool {
+    bytes.iter().all(u8::is_ascii_whitespace)
+}
+
+fn strip_invalid_utf8(bytes: &[u8]) -> Option<Vec<u8>> {
+    std::str::from_utf8(bytes).err()?;
+
+    let mut stripped = Vec::with_capacity(bytes.len());
+    let mut rest = bytes;
+    loop {
+        match std::str::from_utf8(rest) {
+            Ok(valid) => {
+                stripped.extend_from_slice(valid.as_bytes());
+                return Some(stripped);
+            }
+            Err(err) => {
+                let (valid, invalid) = rest.split_at(err.valid_up_to());
+                stripped.extend_from_slice(valid);
+                match err.error_len() {
+                    Some(len) => rest = &invalid[len..],
+                    // The payload ends in the middle of a character.
+                    None => return Some(stripped),
+                }
+            }
+        }
+    }
+}
+
+fn first_document(bytes: &[u8]) -> Option<Vec<u8>> {
+    let end = match scan(bytes) {
+        Scan::Complete(end) => end,
+        _ => return None,
+    };
+    let mut rest = &bytes[end..];
+    if is_blank(rest) {
+        return None;
+    }
+    while !is_blank(rest) {
+        match scan(rest) {
+            Scan::Complete(next) => rest = &rest[next..],
+            _ => return None,
+        }
+    }
+    Some(bytes[..end].to_vec())
+}
+
+fn balance_brackets(bytes: &[u8]) -> Option<Vec<u8>> {
+    let closers = match scan(bytes) {
+        Scan::Open {
+            closers,
+            in_string: false,
+        } => closers,
+        _ => return None,
+    };
+    match bytes.iter().rev().find(|byte| !byte.is_ascii_whitespace()) {
+        Some(b'}') | Some(b']') => {}
+        _ => return None,
+    }
+
+    let mut balanced = bytes.to_vec();
+    balanced.extend(closers.iter().rev());
+    Some(balanced)
+}
@@ -0,0 +1,300 @@
+//! Fault injection, for testing how code built on koit recovers from failures.
+//!
+//! A [`Chaos`](crate::testing::Chaos) configuration is attached to a database with
+//! [`Database::set_chaos`](crate::Database::set_chaos). The database then consults it at every
+//! injection [`Point`](crate::testing::Point) it reaches while saving and reloading, and fails
+//! with the error it would raise if the real operation failed there. A database only exists once
+//! its data is loaded, so loading constructors, including the backup and repair fallbacks of
+//! [`FilePathDatabase`](crate::FilePathDatabase), are not injected into. Failures are scripted, or
+//! drawn from a seeded generator, so test runs are reproducible.
+//!
+//! This module is only available with the `testing` feature. Without it, the injection points
+//! compile away entirely.
+//!
+//! # Examples
+//!
+//! Asserting that save logic retries until it succeeds:
+//!
+//! ```
+//! use koit::{Database, backend::Memory, format::Json, testing::{Chaos, Point}};
+//!
+//! /// The logic under test: saves, retrying up to `attempts` times.
+//! async fn save_with_retry(db: &Database<Vec<u32>, Memory, Json>, attempts: usize) -> koit::Result<usize> {
+//!     let mut attempt = 0;
+//!     loop {
+//!         attempt += 1;
+//!         match db.save().await {
+//!             Ok(_) => return Ok(attempt),
+//!             Err(err) if err.is_backend() && attempt < attempts => continue,
+//!             Err(err) => return Err(err),
+//!         }
+//!     }
+//! }
+//!
+//! #[tokio::main]
+//! async fn main() -> koit::Result<()> {
+//!     let db = Database::<Vec<u32>, Memory, Json>::from_parts(vec![], Memory::new());
+//!     db.set_chaos(Some(Chaos::seeded(0).script(Point::DuringWrite, [true, true])));
+//!
+//!     db.write(|data| data.push(1)).await;
+//!     assert_eq!(save_with_retry(&db, 5).await?, 3);
+//!     assert!(!db.is_dirty());
+//!
+//!     let chaos = db.set_chaos(None).unwrap();
+//!     assert_eq!(chaos.injected(Point::DuringWrite), 2);
+//!
+//!     let (_data, mut backend) = db.into_parts();
+//!     assert_eq!(backend.take(), b"[\n  1\n]");
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use std::collections::VecDeque;
+
+use crate::KoitError;
+
+const POINTS: usize = 5;
+
+/// A point at which a [`Chaos`](crate::testing::Chaos) configuration can inject a failure.
+///
+/// Points are reached in the order listed. Once a failure is injected, the operation stops, and
+/// later points are not reached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Point {
+    /// Before a save encodes the data. Fails with
+    /// [`KoitError::ToFormat`](crate::KoitError::ToFormat).
+    BeforeSerialize,
+    /// After a save encoded the data, before it writes to the backend. Fails with
+    /// [`KoitError::BackendWrite`](crate::KoitError::BackendWrite), leaving the backend
+    /// untouched.
+    BeforeWrite,
+    /// While a save writes to the backend. Only the first half of the encoded data is written,
+    /// then the save fails with [`KoitError::BackendWrite`](crate::KoitError::BackendWrite).
+    DuringWrite,
+    /// After a save wrote the data, before it is made durable; not reached by
+    /// [`Durability::Buffered`](crate::Durability::Buffered) saves, but also reached by
+    /// [`Database::flush`](crate::Database::flush). Fails with
+    /// [`KoitError::BackendWrite`](crate::KoitError::BackendWrite).
+    BeforeFlush,
+    /// While a [reload](crate::Database::reload) or
+    /// [`Database::reload_if_changed`](crate::Database::reload_if_changed) decodes the data read
+    /// from the backend. Fails with [`KoitError::FromFormat`](crate::KoitError::FromFormat),
+    /// leaving the data untouched. Only reloads reach this point; loading constructors do not.
+    DuringParse,
+}
+
+impl Point {
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// The source of an error raised by a [`Chaos`](crate::testing::Chaos) configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("a fault was injected at {point:?}")]
+pub struct InjectedFault {
+    point: Point,
+}
+
+impl InjectedFault {
+    /// The point the fault was injected at.
+    pub fn point(&self) -> Point {
+        self.point
+    }
+
+    /// The error the database raises for this fault.
+    pub(crate) fn into_error(self) -> KoitError {
+        match self.point {
+            Point::BeforeSerialize => KoitError::ToFormat(self.into()),
+            Point::BeforeWrite | Point::DuringWrite | Point::BeforeFlush => {
+                KoitError::BackendWrite(self.into())
+            }
+            Point::DuringParse => KoitError::FromFormat(self.into()),
+        }
+    }
+}
+
+/// A fault injection configuration. See the [module documentation](crate::testing).
+///
+/// Each time a point is reached, its script decides whether to fail, until the script runs out.
+/// Then a failure is drawn with the probability of the point, which is zero by default.
+///
+/// # Examples
+///
+/// ```
+/// use koit::testing::{Chaos, Point};
+///
+/// let draw = |seed| {
+///     let mut chaos = Chaos::seeded(seed).probability(Point::BeforeWrite, 0.5);
+///     (0..64)
+///         .map(|_| chaos.inject(Point::BeforeWrite).is_some())
+///         .collect::<Vec<_>>()
+/// };
+///
+/// // Failures are reproducible.
+/// assert_eq!(draw(42), draw(42));
+/// assert_ne!(draw(42), draw(43));
+/// assert!(draw(42).contains(&true) && draw(42).contains(&false));
+///
+/// // Scripts take precedence.
+/// let mut chaos = Chaos::seeded(1)
+///     .probability(Point::DuringParse, 1.0)
+///     .script(Point::DuringParse, [false, true]);
+/// assert_eq!(chaos.inject(Point::DuringParse), None);
+/// assert_eq!(chaos.inject(Point::DuringParse).unwrap().point(), Point::DuringParse);
+/// assert!(chaos.inject(Point::DuringParse).is_some());
+/// assert_eq!(chaos.injected(Point::DuringParse), 2);
+/// assert_eq!(chaos.inject(Point::BeforeFlush), None);
+/// ```
+///
+/// Every point raises the error of the layer that failed:
+///
+/// ```
+/// use koit::{Database, KoitError, backend::Memory, format::Json, testing::{Chaos, Point}};
+///
+/// #[tokio::main]
+/// async fn main() -> koit::Result<()> {
+///     let db = Database::<String, Memory, Json>::from_parts("a cat".to_owned(), Memory::new());
+///     db.save().await?;
+///
+///     let fail = |point| Some(Chaos::seeded(0).script(point, [true]));
+///
+///     db.set_chaos(fail(Point::BeforeSerialize));
+///     assert!(matches!(db.save().await, Err(KoitError::ToFormat(_))));
+///
+///     db.set_chaos(fail(Point::BeforeWrite));
+///     assert!(matches!(db.save().await, Err(KoitError::BackendWrite(_))));
+///     assert_eq!(db.reload().await?, "a cat");
+///
+///     db.set_chaos(fail(Point::DuringWrite));
+///     assert!(matches!(db.save().await, Err(KoitError::BackendWrite(_))));
+///     // The write was torn.
+///     assert!(matches!(db.reload().await, Err(KoitError::FromFormat(_))));
+///
+///     db.set_chaos(fail(Point::BeforeFlush));
+///     assert!(matches!(db.save().await, Err(KoitError::BackendWrite(_))));
+///     assert!(matches!(db.flush().await, Ok(())));
+///
+///     db.set_chaos(fail(Point::DuringParse));
+///     match db.reload().await {
+///         Err(KoitError::FromFormat(err)) => assert_eq!(
+///             err.downcast_ref::<koit::testing::InjectedFault>().unwrap().point(),
+///             Point::DuringParse
+///         ),
+///         _ => panic!("expected an injected decoding failure"),
+///     }
+///     assert_eq!(db.reload().await?, "a cat");
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Chaos {
+    generator: Generator,
+    probabilities: [f64; POINTS],
+    scripts: [VecDeque<bool>; POINTS],
+    injected: [usize; POINTS],
+}
+
+impl Chaos {
+    /// Create a configuration that injects no failures, drawing from a generator seeded with
+    /// the given seed.
+    pub fn seeded(seed: u64) -> Self {
+        Self {
+            generator: Generator::seeded(seed),
+            probabilities: [0.0; POINTS],
+            scripts: Default::default(),
+            injected: [0; POINTS],
+        }
+    }
+
+    /// Fail at the given point with the given probability, once its script ran out.
+    pub fn probability(mut self, point: Point, probability: f64) -> Self {
+        self.probabilities[point.index()] = probability;
+        self
+    }
+
+    /// Append outcomes to the script of the given point: each time the point is reached, the
+    /// next outcome decides whether to fail.
+    pub fn script<I>(mut self, point: Point, outcomes: I) -> Self
+    where
+        I: IntoIterator<Item = bool>,
+    {
+        self.scripts[point.index()].extend(outcomes);
+        self
+    }
+
+    /// The number of failures injected at the given point so far.
+    pub fn injected(&self, point: Point) -> usize {
+        self.injected[point.index()]
+    }
+
+    /// Reach the given point, returning the fault if a failure is to be injected.
+    pub fn inject(&mut self, point: Point) -> Option<InjectedFault> {
+        let index = point.index();
+        let fail = match self.scripts[index].pop_front() {
+            Some(fail) => fail,
+            None => {
+                self.probabilities[index] > 0.0
+                    && self.generator.next_f64() < self.probabilities[index]
+            }
+        };
+        if fail {
+            self.injected[index] += 1;
+            Some(InjectedFault { point })
+        } else {
+            None
+        }
+    }
+}
+
+/// A seeded pseudo-random number generator (xorshift64*), for reproducible tests. Not suitable
+/// for cryptography.
+///
+/// # Examples
+///
+/// ```
+/// use koit::testing::Generator;
+///
+/// let mut first = Generator::seeded(3);
+/// let mut second = Generator::seeded(3);
+/// assert_eq!(first.next_u64(), second.next_u64());
+/// assert!(first.below(10) < 10);
+/// assert!((0.0..1.0).contains(&first.next_f64()));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Generator {
+    state: u64,
+}
+
+impl Generator {
+    /// Create a generator seeded with the given seed.
+    pub fn seeded(seed: u64) -> Self {
+        Self {
+            // Xorshift never leaves a zero state.
+            state: (seed ^ 0x9e37_79b9_7f4a_7c15).max(1),
+        }
+    }
+
+    /// A uniform draw from all `u64` values.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// A uniform draw from `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// A draw from `[0, bound)`, or zero if `bound` is zero.
+    pub fn below(&mut self, bound: usize) -> usize {
+        match bound {
+            0 => 0,
+            bound => (self.next_u64() % bound as u64) as usize,
+        }
+    }
+}
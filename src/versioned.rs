@@ -0,0 +1,159 @@
+//! Support for enum-versioned data roots.
+//!
+//! A common migration style is to keep every historical version of the data as a variant of an
+//! enum, such as `enum Data { V1(V1), V2(V2) }`, and to always upgrade to the newest variant
+//! after loading. Implementing [`Latest`](crate::versioned::Latest) on such an enum allows using
+//! the [`Upgrade`](crate::versioned::Upgrade) format, so the database holds just the newest
+//! version's inner type.
+//!
+//! # Examples
+//!
+//! ```
+//! use koit::{Database, backend::Memory, format::Json, versioned::{Latest, Upgrade}};
+//! use serde::{Deserialize, Serialize, Serializer};
+//!
+//! #[derive(Deserialize, Serialize)]
+//! struct V1 {
+//!     name: String,
+//! }
+//!
+//! #[derive(Deserialize, Serialize)]
+//! struct V2 {
+//!     first_name: String,
+//!     last_name: String,
+//! }
+//!
+//! #[derive(Deserialize, Serialize)]
+//! enum Data {
+//!     V1(V1),
+//!     V2(V2),
+//! }
+//!
+//! impl Latest for Data {
+//!     type Latest = V2;
+//!
+//!     fn upgrade(self) -> V2 {
+//!         match self {
+//!             Data::V1(v1) => {
+//!                 let mut names = v1.name.splitn(2, ' ');
+//!                 V2 {
+//!                     first_name: names.next().unwrap_or_default().to_owned(),
+//!                     last_name: names.next().unwrap_or_default().to_owned(),
+//!                 }
+//!             }
+//!             Data::V2(v2) => v2,
+//!         }
+//!     }
+//!
+//!     fn is_latest(&self) -> bool {
+//!         matches!(self, Data::V2(_))
+//!     }
+//!
+//!     fn serialize_latest<S: Serializer>(latest: &V2, serializer: S) -> Result<S::Ok, S::Error> {
+//!         serializer.serialize_newtype_variant("Data", 1, "V2", latest)
+//!     }
+//! }
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let db: Database<V2, Memory, Upgrade<Data, Json>> = Database::from_parts(
+//!         V2 { first_name: "Ada".to_owned(), last_name: "Lovelace".to_owned() },
+//!         Memory::from(br#"{ "V1": { "name": "Grace Hopper" } }"#.to_vec()),
+//!     );
+//!     db.reload().await?;
+//!     assert_eq!(db.read(|data| data.last_name.clone()).await, "Hopper");
+//!
+//!     db.save().await?;
+//!     let (_data, mut backend) = db.into_parts();
+//!     assert!(String::from_utf8(backend.take())?.contains("\"V2\""));
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use std::marker::PhantomData;
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::format::Format;
+use crate::KoitError;
+
+/// Trait implementable by enums holding every version of the data.
+///
+/// # Examples
+///
+/// See the [versioned module documentation](crate::versioned).
+pub trait Latest: Sized {
+    /// The data type of the newest version.
+    type Latest;
+
+    /// Upgrade the data to the newest version.
+    fn upgrade(self) -> Self::Latest;
+
+    /// Whether the data is already at the newest version.
+    fn is_latest(&self) -> bool;
+
+    /// Encode data of the newest version as its variant of the enum, without taking ownership
+    /// of it. For a newtype variant `V2` at index 1 of the enum `Data`, as derived by serde, this
+    /// is `serializer.serialize_newtype_variant("Data", 1, "V2", latest)`.
+    ///
+    /// # Errors
+    ///
+    /// If the serializer fails, its error is returned.
+    fn serialize_latest<S: Serializer>(
+        latest: &Self::Latest,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>;
+}
+
+/// Data of the newest version of `E`, borrowed to be [encoded](crate::versioned::Latest::serialize_latest)
+/// as `E`. Used by [`Upgrade`](crate::versioned::Upgrade); decoding it always fails.
+pub struct AsLatest<'a, E: Latest>(pub &'a E::Latest);
+
+impl<E: Latest> Serialize for AsLatest<'_, E> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        E::serialize_latest(self.0, serializer)
+    }
+}
+
+impl<'de, E: Latest> Deserialize<'de> for AsLatest<'_, E> {
+    fn deserialize<D: Deserializer<'de>>(_deserializer: D) -> Result<Self, D::Error> {
+        Err(de::Error::custom("borrowed data cannot be decoded"))
+    }
+}
+
+/// A [`Format`](crate::format::Format) marker for data stored as the enum `E`, using the format
+/// `F` to encode the enum.
+///
+/// Decoding upgrades the data to the newest version. Encoding always wraps the data in the newest
+/// variant, so binaries that do not know that variant fail loudly rather than misparse. The data
+/// is encoded in place, through [`Latest::serialize_latest`](crate::versioned::Latest::serialize_latest).
+#[derive(Debug)]
+pub struct Upgrade<E, F>(PhantomData<(E, F)>);
+
+impl<E, F> std::default::Default for Upgrade<E, F> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<E, F> Format<E::Latest> for Upgrade<E, F>
+where
+    E: Latest,
+    F: Format<E> + for<'a> Format<AsLatest<'a, E>, Error = <F as Format<E>>::Error>,
+{
+    type Error = <F as Format<E>>::Error;
+
+    fn to_bytes(value: &E::Latest) -> Result<Vec<u8>, Self::Error> {
+        <F as Format<AsLatest<'_, E>>>::to_bytes(&AsLatest(value))
+    }
+    fn from_bytes(data: Vec<u8>) -> Result<E::Latest, Self::Error> {
+        Ok(<F as Format<E>>::from_bytes(data)?.upgrade())
+    }
+    fn to_writer(value: &E::Latest, writer: &mut dyn std::io::Write) -> Result<(), KoitError> {
+        <F as Format<AsLatest<'_, E>>>::to_writer(&AsLatest(value), writer)
+    }
+    fn from_reader(reader: &mut dyn std::io::Read) -> Result<E::Latest, KoitError> {
+        Ok(<F as Format<E>>::from_reader(reader)?.upgrade())
+    }
+}